@@ -1,5 +1,12 @@
-use crate::mem::{NonNull, Cell, size_of};
+use crate::mem::{NonNull, Cell, MaybeUninit, PhantomData, size_of};
 use crate::alloc::{Alloc, GlobalAlloc, Layout};
+use crate::vec::ZVec;
+use crate::string::String;
+
+#[cfg(feature="std")]
+use crate::mem::ManuallyDrop;
+#[cfg(feature="std")]
+use crate::vec::Vec;
 
 
 /// minimum block size.
@@ -17,7 +24,10 @@ const ALLOC_SIZE_MAX: usize = BLOCK_SIZE_MAX - size_of::<BlockHeader>();
 const BLOCK_ALIGN: usize = 16;
 
 
-pub struct Arena {
+pub struct Arena<A: Alloc = GlobalAlloc> {
+    /// the allocator, that blocks are allocated from.
+    alloc: A,
+
     /// - block != NonNull::dangling() iff cap != 0
     block: Cell<NonNull<BlockHeader>>,
 
@@ -34,38 +44,142 @@ pub struct Arena {
 
     /// hint for the maximum block size.
     pub block_size_max: Cell<usize>,
+
+    /// the most recent value, that needs to be dropped.
+    /// - see `alloc_new_drop`.
+    drops: Cell<Option<NonNull<DropEntry>>>,
+
+    /// - the first block is borrowed and must not be freed.
+    ///   see `BufferArena`.
+    borrowed_first: bool,
+
+    /// - no new blocks are allocated, allocations fail instead.
+    ///   see `BufferArena::new_fixed`.
+    fixed: bool,
 }
 
-unsafe impl Send for Arena {}
+unsafe impl<A: Alloc + Send> Send for Arena<A> {}
 
 struct BlockHeader {
     prev: NonNull<BlockHeader>,
     prev_cap: usize,
 }
 
+/// an entry in the arena's drop list.
+/// - lives in the arena, right before the value it drops.
+struct DropEntry {
+    prev: Option<NonNull<DropEntry>>,
+    drop_fn: unsafe fn(NonNull<DropEntry>),
+}
 
-impl Arena {
+#[repr(C)]
+struct WithDrop<T> {
+    entry: DropEntry,
+    value: T,
+}
+
+
+impl Arena<GlobalAlloc> {
+    #[inline(always)]
     pub const fn new() -> Arena {
+        Arena::new_in(GlobalAlloc)
+    }
+}
+
+impl<A: Alloc> Arena<A> {
+    pub const fn new_in(alloc: A) -> Arena<A> {
         Arena {
+            alloc,
             block: Cell::new(NonNull::dangling()),
             cap: Cell::new(0),
             used: Cell::new(0),
             block_size_min: Cell::new(BLOCK_SIZE_MIN),
             block_size_max: Cell::new(BLOCK_SIZE_DEFAULT_MAX),
+            drops: Cell::new(None),
+            borrowed_first: false,
+            fixed: false,
         }
     }
 
+    /// the allocator, that blocks are allocated from.
+    #[inline(always)]
+    pub fn backing(&self) -> &A { &self.alloc }
+
 
     #[inline]
     pub fn alloc_ptr<T>(&self) -> NonNull<T> {
         crate::alloc::alloc_ptr::<T>(self).unwrap()
     }
 
+    /// allocates `value` in the arena.
+    ///
+    /// - `value` is never dropped.
+    ///   use `alloc_new_drop` for types, that need to be dropped.
     #[inline]
     pub fn alloc_new<T>(&self, value: T) -> &mut T {
         unsafe { crate::alloc::alloc_new(self, value).unwrap().as_mut() }
     }
 
+    /// allocates `value` in the arena and drops it,
+    /// when the arena is reset, restored, or dropped.
+    ///
+    /// - values are dropped in reverse order of allocation.
+    #[inline]
+    pub fn alloc_new_drop<T: Send + 'static>(&self, value: T) -> &mut T {
+        // `T: 'static`, so `value` can't borrow anything, that could be
+        // freed before the arena.
+        // `T: Send`, so it's fine if the arena is dropped on another thread.
+        unsafe { self.alloc_new_drop_unck(value) }
+    }
+
+    /// allocates `value` in the arena and drops it,
+    /// when the arena is reset, restored, or dropped.
+    ///
+    /// - values are dropped in reverse order of allocation.
+    ///
+    /// # safety:
+    /// - it must be valid to drop `value` at any point
+    ///   until the arena is reset, restored, or dropped.
+    ///   in particular, any borrows in `value` must outlive that point.
+    /// - if `T: !Send`, the arena must be reset, restored, or dropped
+    ///   on the current thread.
+    pub unsafe fn alloc_new_drop_unck<T>(&self, value: T) -> &mut T {
+        if !crate::mem::needs_drop::<T>() {
+            return self.alloc_new(value);
+        }
+
+        unsafe fn drop_fn<T>(entry: NonNull<DropEntry>) { unsafe {
+            let this = entry.cast::<WithDrop<T>>().as_ptr();
+            crate::mem::drop_in_place(&mut (*this).value);
+        }}
+
+        let this = self.alloc_new(WithDrop {
+            entry: DropEntry { prev: self.drops.get(), drop_fn: drop_fn::<T> },
+            value,
+        });
+        self.drops.set(Some(NonNull::from(&mut this.entry)));
+        return &mut this.value;
+    }
+
+    /// drops the values in the drop list until `until`.
+    ///
+    /// # safety:
+    /// - `until` must be `None` or in the drop list.
+    /// - the dropped values must no longer be used.
+    unsafe fn run_drops(&self, until: Option<NonNull<DropEntry>>) {
+        while let Some(entry) = self.drops.get() {
+            if Some(entry) == until {
+                break;
+            }
+
+            // unlink first, in case `drop_fn` panics.
+            unsafe {
+                self.drops.set(entry.as_ref().prev);
+                (entry.as_ref().drop_fn)(entry);
+            }
+        }
+    }
+
     #[inline]
     pub fn alloc_str<'a>(&'a self, value: &str) -> &'a str {
         unsafe {
@@ -76,6 +190,52 @@ impl Arena {
         }
     }
 
+    /// allocates a formatted string in the arena.
+    ///
+    /// - usage: `arena.alloc_fmt(fmt!(..))`.
+    #[inline]
+    pub fn alloc_fmt(&self, args: crate::fmt::Arguments) -> &str {
+        String::from_fmt_in(self, args).leak()
+    }
+
+    #[inline]
+    pub fn alloc_slice_copy<T: Copy>(&self, values: &[T]) -> &mut [T] {
+        unsafe {
+            let ptr = crate::alloc::alloc_array::<T>(self, values.len()).unwrap();
+            core::ptr::copy_nonoverlapping(values.as_ptr(), ptr.as_ptr(), values.len());
+            core::slice::from_raw_parts_mut(ptr.as_ptr(), values.len())
+        }
+    }
+
+    #[inline]
+    pub fn alloc_slice_clone<T: Clone>(&self, values: &[T]) -> &mut [T] {
+        self.alloc_slice_fill_with(values.len(), |i| values[i].clone())
+    }
+
+    /// allocates a slice of length `len`,
+    /// where the `i`-th element is initialized to `f(i)`.
+    ///
+    /// - if `f` panics, the initialized elements are leaked.
+    pub fn alloc_slice_fill_with<T>(&self, len: usize, mut f: impl FnMut(usize) -> T) -> &mut [T] {
+        unsafe {
+            let ptr = crate::alloc::alloc_array::<T>(self, len).unwrap();
+            for i in 0..len {
+                ptr.as_ptr().add(i).write(f(i));
+            }
+            core::slice::from_raw_parts_mut(ptr.as_ptr(), len)
+        }
+    }
+
+    /// collects `iter` into a slice in the arena.
+    ///
+    /// - if the iterator's size hint is too small,
+    ///   the slice is grown in place, if it's the most recent allocation.
+    pub fn alloc_from_iter<T, I: IntoIterator<Item = T>>(&self, iter: I) -> &mut [T] {
+        let mut vec = ZVec::new_in(self);
+        vec.extend(iter);
+        return vec.leak_slice();
+    }
+
 
     /// # safety:
     /// - `layout.size() > 0`.
@@ -83,6 +243,10 @@ impl Arena {
     unsafe fn alloc_slow_path(&self, layout: Layout) -> Option<NonNull<u8>> {
         assert!(layout.size() > 0);
 
+        if self.fixed {
+            return None;
+        }
+
         let padded_size = layout.size().checked_add(layout.align() - 1)?;
         if padded_size > ALLOC_SIZE_MAX {
             return None;
@@ -105,7 +269,7 @@ impl Arena {
         // allocate block.
         crate::static_assert!(align_of::<BlockHeader>() <= BLOCK_ALIGN);
         let block_layout = unsafe { Layout::from_size_align_unchecked(new_cap, BLOCK_ALIGN) };
-        let block: NonNull<BlockHeader> = GlobalAlloc.alloc(block_layout)?.cast();
+        let block: NonNull<BlockHeader> = self.alloc.alloc(block_layout)?.cast();
         assert!(block.is_aligned());
 
         // save current state.
@@ -152,6 +316,9 @@ impl Arena {
     }
 
     pub fn reset_core(&self, including_first: bool) {
+        // all allocations are freed.
+        unsafe { self.run_drops(None) };
+
         if self.cap.get() == 0 {
             return;
         }
@@ -164,13 +331,13 @@ impl Arena {
         while cap > 0 {
             let header = unsafe { block.as_ptr().read() };
 
-            if header.prev_cap == 0 && !including_first {
+            if header.prev_cap == 0 && (!including_first || self.borrowed_first) {
                 break;
             }
 
             unsafe {
                 let block_layout = Layout::from_size_align_unchecked(cap, BLOCK_ALIGN);
-                GlobalAlloc.free(block.cast(), block_layout);
+                self.alloc.free(block.cast(), block_layout);
             }
 
             block = header.prev;
@@ -188,6 +355,271 @@ impl Arena {
         self.cap.set(cap);
         self.used.set(used);
     }
+
+
+    /// saves the current state of the arena.
+    ///
+    /// - see `restore`.
+    #[inline]
+    pub fn save(&self) -> ArenaMark {
+        ArenaMark { block: self.block.get(), used: self.used.get(), drops: self.drops.get() }
+    }
+
+    /// restores a previously saved state.
+    ///
+    /// - frees all blocks that were allocated after `mark` was saved.
+    /// - the current block is kept, if it's the one `mark` refers to.
+    /// - drops the values allocated with `alloc_new_drop` after `mark` was saved.
+    ///
+    /// # safety:
+    /// - `mark` must have been returned by `self.save()`.
+    /// - the arena must not have been restored to a state before `mark`
+    ///   or reset since.
+    /// - allocations made after `mark` was saved must no longer be used.
+    pub unsafe fn restore(&self, mark: ArenaMark) {
+        let ArenaMark { block, used, drops } = mark;
+
+        // `drops` is still in the list, cause the arena hasn't been
+        // restored to an earlier state or reset.
+        unsafe { self.run_drops(drops) };
+
+        let mut cur_block = self.block.get();
+        let mut cap = self.cap.get();
+
+        while cap > 0 && cur_block != block {
+            crate::asan::unpoison(cur_block.as_ptr().cast(), cap);
+
+            let header = unsafe { cur_block.as_ptr().read() };
+
+            unsafe {
+                let block_layout = Layout::from_size_align_unchecked(cap, BLOCK_ALIGN);
+                self.alloc.free(cur_block.cast(), block_layout);
+            }
+
+            cur_block = header.prev;
+            cap = header.prev_cap;
+        }
+
+        let used = if cap > 0 { used } else { 0 };
+        assert!(used <= cap);
+        assert!(used >= size_of::<BlockHeader>() || cap == 0);
+
+        crate::asan::poison(
+            unsafe { cur_block.as_ptr().cast::<u8>().add(used) },
+            cap - used);
+
+        self.block.set(cur_block);
+        self.cap.set(cap);
+        self.used.set(used);
+    }
+}
+
+/// frees all blocks and runs the pending drops.
+///
+/// - previously, dropping an arena leaked its blocks.
+///   use `ManuallyDrop` or `mem::forget` to get that behavior.
+/// - the thread local temp arenas are dropped on thread exit,
+///   which relies on this.
+impl<A: Alloc> Drop for Arena<A> {
+    fn drop(&mut self) {
+        self.reset_all();
+    }
+}
+
+
+/// an arena, whose first block is a caller provided buffer.
+///
+/// - derefs to the underlying `Arena`.
+/// - when the buffer is exhausted, blocks are allocated from `A`,
+///   unless the arena was created with `new_fixed`.
+/// - the buffer is never freed.
+///   resetting the arena keeps the buffer as the first block.
+pub struct BufferArena<'a, A: Alloc = GlobalAlloc> {
+    arena: Arena<A>,
+    phantom: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+impl<'a> BufferArena<'a, GlobalAlloc> {
+    /// creates an arena, that falls back to `GlobalAlloc`,
+    /// when `buffer` is exhausted.
+    #[inline]
+    pub fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self::new_in(GlobalAlloc, buffer)
+    }
+
+    /// creates an arena, whose allocations fail,
+    /// when `buffer` is exhausted.
+    ///
+    /// - the arena never allocates.
+    #[inline]
+    pub fn new_fixed(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let mut this = Self::new_in(GlobalAlloc, buffer);
+        this.arena.fixed = true;
+        return this;
+    }
+}
+
+impl<'a, A: Alloc> BufferArena<'a, A> {
+    /// creates an arena, that falls back to `alloc`,
+    /// when `buffer` is exhausted.
+    ///
+    /// - `buffer` is aligned to the block alignment.
+    ///   if it's too small to hold a block header, it's not used.
+    pub fn new_in(alloc: A, buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        let mut arena = Arena::new_in(alloc);
+
+        let begin = buffer.as_mut_ptr().cast::<u8>();
+        let offset = begin.align_offset(BLOCK_ALIGN);
+        if offset <= buffer.len() && buffer.len() - offset >= size_of::<BlockHeader>() {
+            let cap = (buffer.len() - offset).min(BLOCK_SIZE_MAX);
+
+            unsafe {
+                // `offset + cap <= buffer.len()`.
+                let block = begin.add(offset).cast::<BlockHeader>();
+                block.write(BlockHeader {
+                    prev: NonNull::dangling(),
+                    prev_cap: 0,
+                });
+
+                let used = size_of::<BlockHeader>();
+                crate::asan::poison(block.cast::<u8>().add(used), cap - used);
+
+                arena.block.set(NonNull::new_unchecked(block));
+                arena.cap.set(cap);
+                arena.used.set(used);
+            }
+
+            arena.borrowed_first = true;
+        }
+
+        return Self { arena, phantom: PhantomData };
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        self.arena.reset();
+    }
+
+    #[inline]
+    pub fn scope(&mut self) -> ArenaScope<'_, A> {
+        self.arena.scope()
+    }
+}
+
+impl<'a, A: Alloc> crate::ops::Deref for BufferArena<'a, A> {
+    type Target = Arena<A>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target { &self.arena }
+}
+
+impl<'a, A: Alloc> Drop for BufferArena<'a, A> {
+    fn drop(&mut self) {
+        self.arena.reset_core(true);
+
+        // detach the buffer.
+        // it's returned to the caller unpoisoned.
+        if self.arena.borrowed_first {
+            let block = self.arena.block.replace(NonNull::dangling());
+            let cap = self.arena.cap.replace(0);
+            self.arena.used.set(0);
+            crate::asan::unpoison(block.as_ptr().cast(), cap);
+        }
+    }
+}
+
+// safe: BufferArena is not Clone.
+unsafe impl<'a, A: Alloc> Alloc for BufferArena<'a, A> {
+    #[inline(always)]
+    unsafe fn alloc_nonzero(&self, layout: Layout) -> Option<NonNull<u8>> {
+        unsafe { self.arena.alloc_nonzero(layout) }
+    }
+
+    #[inline(always)]
+    unsafe fn free_nonzero(&self, ptr: NonNull<u8>, layout: Layout) {
+        unsafe { self.arena.free_nonzero(ptr, layout) }
+    }
+
+    #[inline(always)]
+    unsafe fn try_realloc_nonzero(&self, ptr: NonNull<u8>, old_layout: Layout, new_layout: Layout) -> Result<(), ()> {
+        unsafe { self.arena.try_realloc_nonzero(ptr, old_layout, new_layout) }
+    }
+}
+
+
+/// a saved arena state.
+///
+/// - see `Arena::save` & `Arena::restore`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArenaMark {
+    block: NonNull<BlockHeader>,
+    used: usize,
+    drops: Option<NonNull<DropEntry>>,
+}
+
+
+impl<A: Alloc> Arena<A> {
+    /// creates a scope, that restores the arena's current state on drop.
+    ///
+    /// - all allocations made through the scope are freed,
+    ///   when the scope is dropped.
+    #[inline]
+    pub fn scope(&mut self) -> ArenaScope<'_, A> {
+        // the arena is borrowed mutably for the lifetime of the scope,
+        // so all allocations during the scope are borrowed from the scope.
+        unsafe { self.scope_unck() }
+    }
+
+    /// creates a scope, that restores the arena's current state on drop.
+    ///
+    /// - useful for temporary allocations in arenas,
+    ///   that also hold long lived allocations.
+    ///
+    /// # safety:
+    /// - allocations made through `self` (instead of the scope),
+    ///   while the scope is live, must not be used after the scope is dropped.
+    /// - scopes must be dropped in reverse order of creation.
+    #[inline]
+    pub unsafe fn scope_unck(&self) -> ArenaScope<'_, A> {
+        ArenaScope { arena: self, mark: self.save() }
+    }
+}
+
+
+/// an arena scope.
+///
+/// - see `Arena::scope`.
+pub struct ArenaScope<'a, A: Alloc = GlobalAlloc> {
+    arena: &'a Arena<A>,
+    mark: ArenaMark,
+}
+
+impl<'a, A: Alloc> ArenaScope<'a, A> {
+    /// the state, that's restored, when the scope is dropped.
+    #[inline(always)]
+    pub fn mark(&self) -> ArenaMark { self.mark }
+
+    /// creates a nested scope.
+    #[inline]
+    pub fn scope(&mut self) -> ArenaScope<'_, A> {
+        // `self` is borrowed mutably, so this is the innermost scope.
+        unsafe { self.arena.scope_unck() }
+    }
+}
+
+impl<'a, A: Alloc> crate::ops::Deref for ArenaScope<'a, A> {
+    type Target = Arena<A>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target { self.arena }
+}
+
+impl<'a, A: Alloc> Drop for ArenaScope<'a, A> {
+    #[inline]
+    fn drop(&mut self) {
+        // allocations through the scope are borrowed from the scope.
+        unsafe { self.arena.restore(self.mark) }
+    }
 }
 
 
@@ -211,7 +643,7 @@ impl crate::ops::Sub<ArenaStats> for ArenaStats {
     }
 }
 
-impl Arena {
+impl<A: Alloc> Arena<A> {
     pub fn stats(&self) -> ArenaStats {
         let mut result = ArenaStats {
             blocks: 0,
@@ -237,8 +669,166 @@ impl Arena {
 }
 
 
+#[cfg(feature="std")]
+std::thread_local! {
+    static TEMP_POOL: Cell<Vec<Arena>> = const { Cell::new(Vec::new()) };
+
+    static REC: Arena = const { Arena::new() };
+    static REC_REFS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[cfg(feature="std")]
+impl Arena {
+    /// get a temp arena from the thread local pool.
+    ///
+    /// - the arena is exclusive to the returned guard.
+    /// - when the guard is dropped, the arena is reset
+    ///   and returned to the pool.
+    pub fn tls_get_temp() -> TempArena {
+        let arena = TEMP_POOL.with(|pool| {
+            let mut arenas = pool.take();
+            let arena = arenas.pop();
+            pool.set(arenas);
+            arena
+        });
+
+        TempArena {
+            arena: ManuallyDrop::new(arena.unwrap_or_else(Arena::new)),
+            phantom: PhantomData,
+        }
+    }
+
+    /// get the thread's recursive arena.
+    ///
+    /// - there is exactly one rec arena per thread,
+    ///   which is shared by all rec guards.
+    /// - when a guard is dropped, the arena is restored to
+    ///   the state it had when the guard was created.
+    ///   this frees all allocations made since,
+    ///   including the ones made through other guards.
+    /// - `RecArena::scope` creates nested guards safely.
+    ///
+    /// # safety:
+    /// - guards must be dropped in reverse order of creation.
+    /// - allocations made through a guard, while a later guard is live,
+    ///   must not be used after that later guard is dropped.
+    pub unsafe fn tls_get_rec() -> RecArena<'static> {
+        let arena = REC.with(|arena| NonNull::from(arena));
+        let depth = REC_REFS.with(|refs| {
+            let depth = refs.get() + 1;
+            refs.set(depth);
+            depth
+        });
+
+        RecArena {
+            arena,
+            mark: unsafe { arena.as_ref() }.save(),
+            depth,
+            phantom: PhantomData,
+        }
+    }
+
+    /// number of live rec guards on this thread.
+    pub fn tls_rec_num_refs() -> usize {
+        REC_REFS.with(|refs| refs.get())
+    }
+}
+
+
+/// a temp arena from the thread local pool.
+///
+/// - see `Arena::tls_get_temp`.
+#[cfg(feature="std")]
+pub struct TempArena {
+    arena: ManuallyDrop<Arena>,
+    // not Send.
+    phantom: PhantomData<*mut ()>,
+}
+
+#[cfg(feature="std")]
+impl crate::ops::Deref for TempArena {
+    type Target = Arena;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target { &self.arena }
+}
+
+#[cfg(feature="std")]
+impl Drop for TempArena {
+    fn drop(&mut self) {
+        let mut arena = unsafe { ManuallyDrop::take(&mut self.arena) };
+        arena.reset();
+
+        // during thread exit, the pool may already be gone.
+        // then the arena is just dropped.
+        _ = TEMP_POOL.try_with(move |pool| {
+            let mut arenas = pool.take();
+            arenas.push(arena);
+            pool.set(arenas);
+        });
+    }
+}
+
+
+/// a guard for the thread's recursive arena.
+///
+/// - see `Arena::tls_get_rec`.
+#[cfg(feature="std")]
+pub struct RecArena<'a> {
+    arena: NonNull<Arena>,
+    mark: ArenaMark,
+    depth: usize,
+    // not Send.
+    // borrows the parent guard, if created by `scope`.
+    phantom: PhantomData<(*mut (), &'a mut ())>,
+}
+
+#[cfg(feature="std")]
+impl<'a> RecArena<'a> {
+    /// creates a nested guard.
+    #[inline]
+    pub fn scope(&mut self) -> RecArena<'_> {
+        // `self` is borrowed mutably, so this is the innermost guard,
+        // and `self` can't allocate, until the new guard is dropped.
+        unsafe { Arena::tls_get_rec() }
+    }
+}
+
+#[cfg(feature="std")]
+impl<'a> crate::ops::Deref for RecArena<'a> {
+    type Target = Arena;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        // the guard is not Send, so the thread local is still alive.
+        unsafe { self.arena.as_ref() }
+    }
+}
+
+#[cfg(feature="std")]
+impl<'a> Drop for RecArena<'a> {
+    fn drop(&mut self) {
+        // no assert. panicking here during an unwind would abort.
+        debug_assert_eq!(self.depth, Arena::tls_rec_num_refs(),
+            "rec arena guards must be dropped in reverse order");
+
+        if self.depth == 1 {
+            // outermost guard: keep the first block for the next user.
+            self.reset_core(false);
+        }
+        else {
+            // allocations since `mark` are borrowed from this guard,
+            // and all later guards have been dropped.
+            unsafe { self.restore(self.mark) }
+        }
+
+        REC_REFS.with(|refs| refs.set(self.depth - 1));
+    }
+}
+
+
 // safe: Arena is not Clone.
-unsafe impl Alloc for Arena {
+unsafe impl<A: Alloc> Alloc for Arena<A> {
     unsafe fn alloc_nonzero(&self, layout: Layout) -> Option<NonNull<u8>> {
         debug_assert!(layout.size() > 0);
 
@@ -317,7 +907,7 @@ unsafe impl Alloc for Arena {
 mod tests {
     use super::*;
 
-    fn get_base(arena: &Arena) -> Option<usize> {
+    fn get_base<A: Alloc>(arena: &Arena<A>) -> Option<usize> {
         if arena.cap.get() != 0 {
             Some(arena.block.get().as_ptr() as usize)
         }
@@ -484,180 +1074,483 @@ mod tests {
         assert!(err.is_err());
     }
 
-    /* broken
+    #[test]
+    fn arena_drop_frees_blocks() {
+        struct Counting<'a> { live: &'a Cell<usize> }
+
+        unsafe impl<'a> Alloc for Counting<'a> {
+            unsafe fn alloc_nonzero(&self, layout: Layout) -> Option<NonNull<u8>> {
+                self.live.set(self.live.get() + layout.size());
+                unsafe { GlobalAlloc.alloc_nonzero(layout) }
+            }
+
+            unsafe fn free_nonzero(&self, ptr: NonNull<u8>, layout: Layout) {
+                self.live.set(self.live.get() - layout.size());
+                unsafe { GlobalAlloc.free_nonzero(ptr, layout) }
+            }
+        }
+
+        let live = Cell::new(0);
+        let drops = std::sync::Arc::new(());
+
+        // new: dropping frees the blocks and runs the drops.
+        {
+            let arena = Arena::new_in(Counting { live: &live });
+            arena.alloc_new([0u8; 100]);
+            arena.alloc_new_drop(drops.clone());
+            crate::alloc::alloc_array::<u8>(&arena, 64*1024).unwrap();
+            assert_eq!(arena.stats().blocks, 2);
+            assert_eq!(live.get(), arena.stats().allocated as usize);
+            assert_eq!(std::sync::Arc::strong_count(&drops), 2);
+        }
+        assert_eq!(live.get(), 0);
+        assert_eq!(std::sync::Arc::strong_count(&drops), 1);
+
+        // old: not dropping the arena leaks everything.
+        let mut arena = ManuallyDrop::new(Arena::new_in(Counting { live: &live }));
+        arena.alloc_new([0u8; 100]);
+        arena.alloc_new_drop(drops.clone());
+        let allocated = arena.stats().allocated as usize;
+        assert_eq!(live.get(), allocated);
+        assert_eq!(std::sync::Arc::strong_count(&drops), 2);
+
+        // don't actually leak.
+        unsafe { ManuallyDrop::drop(&mut arena) };
+        assert_eq!(live.get(), 0);
+    }
+
+    #[test]
+    fn arena_tls_temp() {
+        let (a_base, b_base) = {
+            let a = Arena::tls_get_temp();
+            let b = Arena::tls_get_temp();
+
+            let x = a.alloc_new(1u32);
+            let y = b.alloc_new(2u32);
+            assert_eq!((*x, *y), (1, 2));
+
+            (get_base(&a).unwrap(), get_base(&b).unwrap())
+        };
+        assert_ne!(a_base, b_base);
+
+        // temp arenas are reused & reset.
+        let c = Arena::tls_get_temp();
+        let c_base = get_base(&c).unwrap();
+        assert!(c_base == a_base || c_base == b_base);
+        assert_eq!(c.stats().blocks, 1);
+        assert_eq!(c.stats().used as usize, size_of::<BlockHeader>());
+    }
+
+    #[test]
+    fn arena_tls_rec() {
+        assert_eq!(Arena::tls_rec_num_refs(), 0);
+
+        let mut outer = unsafe { Arena::tls_get_rec() };
+        assert_eq!(Arena::tls_rec_num_refs(), 1);
+
+        let x = outer.alloc_new(1u8);
+        let used = outer.stats().used;
+
+        {
+            // like from a callee. `outer` isn't used, while `inner` is live.
+            let inner = unsafe { Arena::tls_get_rec() };
+            assert_eq!(Arena::tls_rec_num_refs(), 2);
+
+            inner.alloc_new([0u8; 64]);
+            crate::alloc::alloc_array::<u8>(&*inner, 4096).unwrap();
+            assert_eq!(inner.stats().blocks, 2);
+        }
+        assert_eq!(Arena::tls_rec_num_refs(), 1);
+
+        // inner allocations were freed.
+        assert_eq!(outer.stats().blocks, 1);
+        assert_eq!(outer.stats().used, used);
+        assert_eq!(*x, 1);
+
+        // safe nesting.
+        {
+            let inner = outer.scope();
+            assert_eq!(Arena::tls_rec_num_refs(), 2);
+            inner.alloc_new([0u8; 64]);
+        }
+        assert_eq!(outer.stats().used, used);
+
+        drop(outer);
+        assert_eq!(Arena::tls_rec_num_refs(), 0);
+
+        // nested calls, like from a callee.
+        let again = unsafe { Arena::tls_get_rec() };
+        {
+            let nested = unsafe { Arena::tls_get_rec() };
+            assert_eq!(Arena::tls_rec_num_refs(), 2);
+            assert_eq!(*nested.alloc_new(3u32), 3);
+        }
+        assert_eq!(again.stats().blocks, 1);
+        assert_eq!(again.stats().used as usize, size_of::<BlockHeader>());
+    }
+
     #[test]
     fn arena_realloc_edge_cases() {
         let backing = Arena::new();
-        backing.min_block_size.set(1024);
+        backing.block_size_min.set(8*1024);
 
-        let arena = Arena::new();
-        arena.min_block_size.set(128);
-        arena.max_block_size.set(128);
+        let arena = Arena::new_in(&backing);
+        arena.block_size_min.set(1024);
+        arena.block_size_max.set(1024);
 
         let layout_1 = Layout::from_size_align(1, 1).unwrap(); // must be 1,1.
         let layout_8 = Layout::from_size_align(8, 1).unwrap();
 
         // no block.
-        assert_eq!(arena.stats().total_allocated, 0);
+        assert_eq!(arena.stats().allocated, 0);
         let err = unsafe { arena.try_realloc(NonNull::new(&mut 1u8).unwrap(), layout_1, layout_8) };
         assert!(err.is_err());
 
-        let before = backing.alloc_ptr::<[u8; 128]>().cast::<u8>();
+        let before = backing.alloc_ptr::<[u8; 1024]>().cast::<u8>();
 
         // with block.
         let ptr = arena.alloc_ptr::<u8>();
         let stats = arena.stats();
-        assert_eq!(stats.total_allocated, 128);
-        assert_eq!(stats.num_blocks, 1);
+        assert_eq!(stats.allocated, 1024);
+        assert_eq!(stats.blocks, 1);
 
-        let after = backing.alloc_ptr::<[u8; 128]>().cast::<u8>();
-        assert_eq!(after.as_ptr() as usize - before.as_ptr() as usize, 128);
+        // block was allocated from `backing`.
+        let after = backing.alloc_ptr::<[u8; 1024]>().cast::<u8>();
+        assert_eq!(after.as_ptr() as usize - before.as_ptr() as usize, 2*1024);
 
         // out of bounds pointers.
         let err = unsafe { arena.try_realloc(before, layout_1, layout_8) };
         assert!(err.is_err());
         let err = unsafe { arena.try_realloc(after, layout_1, layout_8) };
         assert!(err.is_err());
-        let err = unsafe { arena.try_realloc(NonNull::new(after.as_ptr().add(128)).unwrap(), layout_1, layout_8) };
+        let err = unsafe { arena.try_realloc(NonNull::new(after.as_ptr().add(1024)).unwrap(), layout_1, layout_8) };
         assert!(err.is_err());
 
         // can't resize beyond end of block.
-        let layout_too_full = Layout::from_size_align(128 - size_of::<BlockHeader>() + 1, 1).unwrap();
+        let layout_too_full = Layout::from_size_align(1024 - size_of::<BlockHeader>() + 1, 1).unwrap();
         let err = unsafe { arena.try_realloc(ptr, layout_1, layout_too_full) };
         assert!(err.is_err());
 
         // can resize to end of block.
-        let layout_full = Layout::from_size_align(128 - size_of::<BlockHeader>(), 1).unwrap();
+        let layout_full = Layout::from_size_align(1024 - size_of::<BlockHeader>(), 1).unwrap();
         let ok = unsafe { arena.try_realloc(ptr, layout_1, layout_full) };
         assert!(ok.is_ok());
     }
-    */
 
-    /*
     #[test]
     fn arena_save_restore() {
         let arena = Arena::new();
-        arena.min_block_size.set(64);
-        arena.max_block_size.set(64);
+        arena.block_size_min.set(1024);
+        arena.block_size_max.set(1024);
+
+        let current_block_used = || {
+            if arena.cap.get() != 0 { arena.used.get() } else { 0 }
+        };
 
         let save_0 = arena.save();
-        assert_eq!(save_0.used_end, align_of::<BlockHeader>());
 
-        unsafe { arena.restore(save_0.clone()) };
-        assert_eq!(arena.stats().total_allocated, 0);
-        assert_eq!(arena.stats().num_blocks, 0);
-        assert_eq!(arena.current_block_used(), 0);
+        unsafe { arena.restore(save_0) };
+        assert_eq!(arena.stats().allocated, 0);
+        assert_eq!(arena.stats().blocks, 0);
+        assert_eq!(current_block_used(), 0);
 
-        let a = arena.alloc_ptr::<[u8; 12]>().as_ptr() as usize;
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 12);
+        arena.alloc_ptr::<[u8; 12]>();
+        assert_eq!(arena.stats().allocated, 1024);
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 12);
 
         // same block save.
         let save_1 = arena.save();
-        assert_eq!(save_1.used_end, a + 12);
 
         // restore immediately.
-        unsafe { arena.restore(save_1.clone()) }
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 12);
+        unsafe { arena.restore(save_1) }
+        assert_eq!(arena.stats().allocated, 1024);
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 12);
 
         arena.alloc_ptr::<u32>();
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 12 + 4);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 12 + 4);
 
         // restore same block.
-        unsafe { arena.restore(save_1.clone()) }
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 12);
+        unsafe { arena.restore(save_1) }
+        assert_eq!(arena.stats().allocated, 1024);
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 12);
 
         // allocate rest of block.
-        arena.alloc_ptr::<[u8; 64 - size_of::<BlockHeader>() - 12]>();
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), 64);
+        arena.alloc_ptr::<[u8; 1024 - size_of::<BlockHeader>() - 12]>();
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(current_block_used(), 1024);
 
         // force another block.
-        let b = arena.alloc_ptr::<[u8; 8]>().as_ptr() as usize;
-        assert_eq!(arena.stats().total_allocated, 128);
-        assert_eq!(arena.stats().num_blocks, 2);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 8);
+        arena.alloc_ptr::<[u8; 8]>();
+        assert_eq!(arena.stats().allocated, 2048);
+        assert_eq!(arena.stats().blocks, 2);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 8);
 
         let save_2 = arena.save();
-        assert_eq!(save_2.used_end, b + 8);
-
-        // and an oversided block.
-        let c = arena.alloc_ptr::<[u8; 128]>().as_ptr() as usize;
-        assert_eq!(arena.stats().total_allocated, 128 + size_of::<BlockHeader>()+128);
-        assert_eq!(arena.stats().num_blocks, 3);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>()+128);
 
-        let save_3 = arena.save();
-        assert_eq!(save_3.used_end, c + 128);
+        // and an oversized block.
+        arena.alloc_ptr::<[u8; 2048]>();
+        assert_eq!(arena.stats().allocated as usize, 2048 + size_of::<BlockHeader>() + 2048);
+        assert_eq!(arena.stats().blocks, 3);
 
         // and another normal block.
-        let d = arena.alloc_ptr::<[u8; 8]>().as_ptr() as usize;
-        assert_eq!(arena.stats().total_allocated, 128 + size_of::<BlockHeader>()+128 + 64);
-        assert_eq!(arena.stats().num_blocks, 4);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 8);
-
-        let save_4 = arena.save();
-        assert_eq!(save_4.used_end, d + 8);
+        arena.alloc_ptr::<[u8; 8]>();
+        assert_eq!(arena.stats().blocks, 4);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 8);
 
         // restore skipping 2 blocks.
         unsafe { arena.restore(save_2) }
-        assert_eq!(arena.stats().total_allocated, 128);
-        assert_eq!(arena.stats().num_blocks, 2);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 8);
+        assert_eq!(arena.stats().allocated, 2048);
+        assert_eq!(arena.stats().blocks, 2);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 8);
 
         // restore save_1 again.
         unsafe { arena.restore(save_1) }
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 12);
+        assert_eq!(arena.stats().allocated, 1024);
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(current_block_used(), size_of::<BlockHeader>() + 12);
 
         // restore save_0 again.
         unsafe { arena.restore(save_0) }
-        assert_eq!(arena.stats().total_allocated, 0);
-        assert_eq!(arena.stats().num_blocks, 0);
-        assert_eq!(arena.current_block_used(), 0);
+        assert_eq!(arena.stats().allocated, 0);
+        assert_eq!(arena.stats().blocks, 0);
+        assert_eq!(current_block_used(), 0);
+    }
+
+    #[test]
+    fn arena_scope() {
+        let mut arena = Arena::new();
+
+        // scope without a block frees the block.
+        {
+            let s = arena.scope();
+            s.alloc_new(1u32);
+            assert_eq!(s.stats().blocks, 1);
+        }
+        assert_eq!(arena.stats().blocks, 0);
+
+        let long_lived = arena.alloc_new(42u64);
+        let used_1 = arena.stats().used;
+
+        for i in 0..100 {
+            let mut s = unsafe { arena.scope_unck() };
+            assert_eq!(s.stats().used, used_1);
+
+            let x = s.alloc_new([i; 100]);
+            assert_eq!(x[99], i);
+
+            {
+                let inner = s.scope();
+                crate::alloc::alloc_array::<u8>(&*inner, 2000).unwrap();
+                assert_eq!(inner.stats().blocks, 2);
+            }
+            assert_eq!(s.stats().blocks, 1);
+        }
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(arena.stats().used, used_1);
+        assert_eq!(*long_lived, 42);
     }
-    */
 
-    /* broken
     #[test]
     fn arena_save_restore_contiguous() {
         let backing = Arena::new();
+        backing.block_size_min.set(8*1024);
 
-        #[allow(deprecated)]
         let arena = Arena::new_in(&backing);
-        arena.min_block_size.set(64);
-        arena.max_block_size.set(64);
+        arena.block_size_min.set(1024);
+        arena.block_size_max.set(1024);
 
-        arena.alloc_ptr::<[u8; 64-size_of::<BlockHeader>()]>().as_ptr() as usize;
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), 64);
+        arena.alloc_ptr::<[u8; 1024-size_of::<BlockHeader>()]>();
+        assert_eq!(arena.stats().allocated, 1024);
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(arena.used.get(), 1024);
 
         let save = arena.save();
 
-        arena.alloc_ptr::<[u8; 8]>().as_ptr() as usize;
-        assert_eq!(arena.stats().total_allocated, 128);
-        assert_eq!(arena.stats().num_blocks, 2);
-        assert_eq!(arena.current_block_used(), size_of::<BlockHeader>() + 8);
+        arena.alloc_ptr::<[u8; 8]>();
+        assert_eq!(arena.stats().allocated, 2048);
+        assert_eq!(arena.stats().blocks, 2);
+        assert_eq!(arena.used.get(), size_of::<BlockHeader>() + 8);
 
         // check blocks contiguous.
-        assert_eq!(save.used_end, arena.block.get().as_ptr() as usize);
+        assert_eq!(save.block.as_ptr() as usize + save.used, arena.block.get().as_ptr() as usize);
 
         // correctly frees current block.
         unsafe { arena.restore(save) }
-        assert_eq!(arena.stats().total_allocated, 64);
-        assert_eq!(arena.stats().num_blocks, 1);
-        assert_eq!(arena.current_block_used(), 64);
+        assert_eq!(arena.stats().allocated, 1024);
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(arena.used.get(), 1024);
+    }
+
+    #[test]
+    fn arena_buffer() {
+        let mut buffer = [MaybeUninit::<u8>::uninit(); 512];
+        let begin = buffer.as_ptr() as usize;
+        let end = begin + buffer.len();
+
+        let in_buffer = |ptr: usize| ptr >= begin && ptr < end;
+
+        let mut arena = BufferArena::new(&mut buffer);
+        let cap = arena.stats().allocated as usize;
+        assert!(cap > 512 - BLOCK_ALIGN);
+        assert_eq!(arena.stats().blocks, 1);
+
+        let a = arena.alloc_new(1u64);
+        assert!(in_buffer(a as *mut u64 as usize));
+
+        // in place growth within the buffer.
+        let layout_8 = Layout::new::<u64>();
+        let layout_16 = Layout::from_size_align(16, 8).unwrap();
+        let ok = unsafe { arena.try_realloc(NonNull::from(&mut *a).cast(), layout_8, layout_16) };
+        assert!(ok.is_ok());
+
+        // exhaust buffer -> falls back to heap.
+        let big = crate::alloc::alloc_array::<u8>(&*arena, 1000).unwrap();
+        assert!(!in_buffer(big.as_ptr() as usize));
+        assert_eq!(arena.stats().blocks, 2);
+
+        // reset keeps the buffer.
+        arena.reset();
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(arena.stats().allocated as usize, cap);
+
+        let b = arena.alloc_new(2u64);
+        assert!(in_buffer(b as *mut u64 as usize));
+
+        // reset_core(true) can't free the buffer.
+        arena.reset_core(true);
+        assert_eq!(arena.stats().blocks, 1);
+        assert_eq!(arena.stats().allocated as usize, cap);
+
+        drop(arena);
+
+        // fixed arenas fail when the buffer is exhausted.
+        let arena = BufferArena::new_fixed(&mut buffer);
+        assert!(crate::alloc::alloc_array::<u8>(&arena, 256).is_some());
+        assert!(crate::alloc::alloc_array::<u8>(&arena, 1000).is_none());
+        assert_eq!(arena.stats().blocks, 1);
+
+        // too small buffers are ignored.
+        let mut tiny = [MaybeUninit::<u8>::uninit(); 4];
+        let arena = BufferArena::new_fixed(&mut tiny);
+        assert_eq!(arena.stats().blocks, 0);
+        assert!(arena.alloc(Layout::new::<u8>()).is_none());
+    }
+
+    #[test]
+    fn arena_drop() {
+        use std::vec::Vec;
+        use std::rc::Rc;
+        use std::sync::{Arc, Mutex};
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        struct Logger(u32, Arc<Mutex<Vec<u32>>>);
+        impl Drop for Logger {
+            fn drop(&mut self) { self.1.lock().unwrap().push(self.0); }
+        }
+
+        let take = || core::mem::take(&mut *log.lock().unwrap());
+
+        let mut arena = Arena::new();
+
+        let a = arena.alloc_new_drop(Logger(1, log.clone()));
+        assert_eq!(a.0, 1);
+        arena.alloc_new_drop(Logger(2, log.clone()));
+        // not dropped.
+        arena.alloc_new(Logger(99, log.clone()));
+        let v = arena.alloc_new_drop(vec![1, 2, 3]);
+        v.push(4);
+        arena.alloc_new_drop(Logger(3, log.clone()));
+
+        arena.reset();
+        assert_eq!(take(), [3, 2, 1]);
+
+        // scopes drop their values.
+        arena.alloc_new_drop(Logger(1, log.clone()));
+        {
+            let scope = arena.scope();
+            scope.alloc_new_drop(Logger(2, log.clone()));
+            for i in 0..100 {
+                scope.alloc_new_drop(Logger(10 + i, log.clone()));
+            }
+            drop(scope);
+            let dropped = take();
+            assert_eq!(dropped.len(), 101);
+            assert_eq!(dropped[0], 109);
+            assert_eq!(dropped[100], 2);
+        }
+        assert!(take().is_empty());
+
+        // buffer arenas.
+        let mut buffer = [MaybeUninit::<u8>::uninit(); 256];
+        let buffer_arena = BufferArena::new(&mut buffer);
+        buffer_arena.alloc_new_drop(Logger(2, log.clone()));
+        drop(buffer_arena);
+        assert_eq!(take(), [2]);
+
+        // non-send values.
+        let rc = Rc::new(42);
+        unsafe { arena.alloc_new_drop_unck(rc.clone()) };
+        assert_eq!(Rc::strong_count(&rc), 2);
+
+        drop(arena);
+        assert_eq!(take(), [1]);
+        assert_eq!(Rc::strong_count(&rc), 1);
+    }
+
+    #[test]
+    fn arena_slices() {
+        let arena = Arena::new();
+
+        let a = arena.alloc_slice_copy(&[1u32, 2, 3]);
+        a[0] = 4;
+        assert_eq!(a, &[4, 2, 3]);
+        assert_eq!(arena.alloc_slice_copy::<u8>(&[]), &[]);
+
+        let strs = [std::string::String::from("hi"), "there".into()];
+        let b = arena.alloc_slice_clone(&strs);
+        assert_eq!(b, &strs);
+        // not tracked, free them ourselves.
+        for s in b.iter_mut() { drop(core::mem::take(s)); }
+
+        let c = arena.alloc_slice_fill_with(5, |i| i*i);
+        assert_eq!(c, &[0, 1, 4, 9, 16]);
+
+        let d = arena.alloc_from_iter((0..100u32).map(|i| i as u8));
+        assert_eq!(d.len(), 100);
+        assert!(d.iter().enumerate().all(|(i, v)| *v as usize == i));
+
+        // size hint of zero -> in place growth.
+        let before = arena.stats();
+        let mut i = 0u8;
+        let e = arena.alloc_from_iter(core::iter::from_fn(|| {
+            i += 1;
+            (i <= 200).then_some(i)
+        }));
+        assert_eq!(e.len(), 200);
+        let after = arena.stats() - before;
+        assert_eq!(after.blocks, 0);
+        assert!(after.used < 256 + 16);
+
+        let f = arena.alloc_fmt(crate::fmt!("{} + {} = {}", 1, 2, 1+2));
+        assert_eq!(f, "1 + 2 = 3");
+    }
+
+    #[test]
+    fn arena_nested() {
+        let mut backing = Arena::new();
+        {
+            let arena = Arena::new_in(&backing);
+            arena.alloc_new(1u32);
+            crate::alloc::alloc_array::<u8>(&arena, 4096).unwrap();
+            assert_eq!(arena.stats().blocks, 2);
+            assert!(backing.stats().used as usize > 4096 + 1024);
+        }
+        backing.reset();
+        assert_eq!(backing.stats().used as usize, size_of::<BlockHeader>());
     }
-    */
 }
 
@@ -106,3 +106,272 @@ byte_mask_impl!(ByteMask4, u32, splat_4);
 byte_mask_impl!(ByteMask8, u64, splat_8);
 
 
+
+
+/// byte masks with one bit per byte.
+///
+/// - unlike the swar masks above, these are exact.
+///   every set bit is a match, not just the first one.
+/// - bit `i` corresponds to byte `i`.
+macro_rules! byte_bitmask_impl {
+    ($name:ident, $ty:ident, $n:expr, $eq:ident, $high:ident) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub struct $name($ty);
+
+        impl $name {
+            pub const NONE: Self = Self(0);
+            pub const ALL: Self = Self(<$ty>::MAX);
+
+            #[inline(always)]
+            pub const fn from_bits(bits: $ty) -> Self { Self(bits) }
+
+            #[inline(always)]
+            pub const fn bits(self) -> $ty { self.0 }
+
+
+            #[inline(always)]
+            pub const fn none(self) -> bool { self.0 == 0 }
+
+            #[inline(always)]
+            pub const fn any(self) -> bool { self.0 != 0 }
+
+            #[inline(always)]
+            pub const fn all(self) -> bool { self.0 == <$ty>::MAX }
+
+
+            #[inline(always)]
+            pub fn not(self) -> Self {
+                Self(!self.0)
+            }
+
+
+            #[inline(always)]
+            pub fn find_zero_bytes(value: [u8; $n]) -> Self {
+                Self(simd::$eq(&value, 0))
+            }
+
+            #[inline(always)]
+            pub fn find_equal_bytes(value: [u8; $n], byte: u8) -> Self {
+                Self(simd::$eq(&value, byte))
+            }
+
+            #[inline(always)]
+            pub fn find_high_bit_bytes(value: [u8; $n]) -> Self {
+                Self(simd::$high(&value))
+            }
+        }
+
+        impl Iterator for $name {
+            type Item = usize;
+
+            #[inline(always)]
+            fn next(&mut self) -> Option<Self::Item> {
+                if self.0 != 0 {
+                    let i = self.0.trailing_zeros();
+                    self.0 &= self.0 - 1;
+                    return Some(i as usize);
+                }
+                return None;
+            }
+        }
+
+        impl core::ops::BitAnd for $name {
+            type Output = Self;
+
+            #[inline(always)]
+            fn bitand(self, rhs: Self) -> Self {
+                Self(self.0 & rhs.0)
+            }
+        }
+
+        impl core::ops::BitOr for $name {
+            type Output = Self;
+
+            #[inline(always)]
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+
+        impl core::ops::Not for $name {
+            type Output = Self;
+
+            #[inline(always)]
+            fn not(self) -> Self {
+                Self::not(self)
+            }
+        }
+    }
+}
+
+byte_bitmask_impl!(ByteMask16, u16, 16, equal_16, high_bit_16);
+byte_bitmask_impl!(ByteMask32, u32, 32, equal_32, high_bit_32);
+
+
+mod simd {
+    crate::cfg_if! {
+        if #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))] {
+            use crate::arch::x86_64::*;
+
+            #[inline(always)]
+            pub fn equal_16(value: &[u8; 16], byte: u8) -> u16 { unsafe {
+                let v = _mm_loadu_si128(value.as_ptr().cast());
+                _mm_movemask_epi8(_mm_cmpeq_epi8(v, _mm_set1_epi8(byte as i8))) as u16
+            }}
+
+            #[inline(always)]
+            pub fn high_bit_16(value: &[u8; 16]) -> u16 { unsafe {
+                _mm_movemask_epi8(_mm_loadu_si128(value.as_ptr().cast())) as u16
+            }}
+        }
+        else if #[cfg(all(target_arch = "aarch64", target_feature = "neon"))] {
+            use crate::arch::aarch64::*;
+
+            /// packs a mask of `0x00`/`0xff` bytes into bits.
+            #[inline(always)]
+            unsafe fn to_bits(mask: uint8x16_t) -> u16 { unsafe {
+                const WEIGHTS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+                let bits = vandq_u8(mask, vld1q_u8(WEIGHTS.as_ptr()));
+                let lo = vaddv_u8(vget_low_u8(bits))  as u16;
+                let hi = vaddv_u8(vget_high_u8(bits)) as u16;
+                lo | hi << 8
+            }}
+
+            #[inline(always)]
+            pub fn equal_16(value: &[u8; 16], byte: u8) -> u16 { unsafe {
+                let v = vld1q_u8(value.as_ptr());
+                to_bits(vceqq_u8(v, vdupq_n_u8(byte)))
+            }}
+
+            #[inline(always)]
+            pub fn high_bit_16(value: &[u8; 16]) -> u16 { unsafe {
+                let v = vld1q_u8(value.as_ptr());
+                to_bits(vtstq_u8(v, vdupq_n_u8(0x80)))
+            }}
+        }
+        else {
+            use super::splat_8;
+
+            /// packs the high bits of a swar mask.
+            #[inline(always)]
+            fn to_bits(mask: u64) -> u8 {
+                ((mask >> 7).wrapping_mul(0x0102_0408_1020_4080) >> 56) as u8
+            }
+
+            #[inline(always)]
+            fn word(value: &[u8; 16], i: usize) -> u64 {
+                u64::from_le_bytes(value[8*i..8*i+8].try_into().expect("unreachable"))
+            }
+
+            /// exact high bit mask of the zero bytes.
+            #[inline(always)]
+            fn zero_bytes_exact(word: u64) -> u64 {
+                let non_zero = ((word & splat_8(0x7f)).wrapping_add(splat_8(0x7f))) | word;
+                return !non_zero & splat_8(0x80);
+            }
+
+            #[inline(always)]
+            pub fn equal_16(value: &[u8; 16], byte: u8) -> u16 {
+                let lo = to_bits(zero_bytes_exact(word(value, 0) ^ splat_8(byte))) as u16;
+                let hi = to_bits(zero_bytes_exact(word(value, 1) ^ splat_8(byte))) as u16;
+                lo | hi << 8
+            }
+
+            #[inline(always)]
+            pub fn high_bit_16(value: &[u8; 16]) -> u16 {
+                let lo = to_bits(word(value, 0) & splat_8(0x80)) as u16;
+                let hi = to_bits(word(value, 1) & splat_8(0x80)) as u16;
+                lo | hi << 8
+            }
+        }
+    }
+
+    crate::cfg_if! {
+        if #[cfg(all(target_arch = "x86_64", target_feature = "avx2"))] {
+            #[inline(always)]
+            pub fn equal_32(value: &[u8; 32], byte: u8) -> u32 { unsafe {
+                let v = _mm256_loadu_si256(value.as_ptr().cast());
+                _mm256_movemask_epi8(_mm256_cmpeq_epi8(v, _mm256_set1_epi8(byte as i8))) as u32
+            }}
+
+            #[inline(always)]
+            pub fn high_bit_32(value: &[u8; 32]) -> u32 { unsafe {
+                _mm256_movemask_epi8(_mm256_loadu_si256(value.as_ptr().cast())) as u32
+            }}
+        }
+        else {
+            #[inline(always)]
+            fn halves(value: &[u8; 32]) -> (&[u8; 16], &[u8; 16]) {
+                let (lo, hi) = value.split_at(16);
+                (lo.try_into().expect("unreachable"), hi.try_into().expect("unreachable"))
+            }
+
+            #[inline(always)]
+            pub fn equal_32(value: &[u8; 32], byte: u8) -> u32 {
+                let (lo, hi) = halves(value);
+                equal_16(lo, byte) as u32 | (equal_16(hi, byte) as u32) << 16
+            }
+
+            #[inline(always)]
+            pub fn high_bit_32(value: &[u8; 32]) -> u32 {
+                let (lo, hi) = halves(value);
+                high_bit_16(lo) as u32 | (high_bit_16(hi) as u32) << 16
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_mask_8() {
+        let value = u64::from_le_bytes([1, 0, 3, 0x80, 3, 0xff, 7, 3]);
+        assert_eq!(ByteMask8::find_zero_bytes(value).next(), Some(1));
+        assert_eq!(ByteMask8::find_equal_bytes(value, 3).next(), Some(2));
+        assert_eq!(ByteMask8::find_high_bit_bytes(value).collect::<std::vec::Vec<_>>(), [3, 5]);
+        assert!(ByteMask8::find_equal_bytes(value, 9).none());
+    }
+
+    #[test]
+    fn byte_mask_wide() {
+        let mut bytes = [0u8; 32];
+        for i in 0..32 {
+            bytes[i] = (i as u8).wrapping_mul(37) ^ 0x5a;
+        }
+        bytes[3] = 0; bytes[17] = 0; bytes[31] = 0;
+        bytes[4] = 9; bytes[20] = 9;
+
+        let check = |b: &[u8]| {
+            let expect_eq = |byte: u8| b.iter().enumerate().filter(|(_, v)| **v == byte).map(|(i, _)| i).collect::<std::vec::Vec<_>>();
+            let expect_hi = b.iter().enumerate().filter(|(_, v)| **v >= 0x80).map(|(i, _)| i).collect::<std::vec::Vec<_>>();
+
+            if b.len() == 16 {
+                let v: [u8; 16] = b.try_into().unwrap();
+                assert_eq!(ByteMask16::find_zero_bytes(v).collect::<std::vec::Vec<_>>(), expect_eq(0));
+                assert_eq!(ByteMask16::find_equal_bytes(v, 9).collect::<std::vec::Vec<_>>(), expect_eq(9));
+                assert_eq!(ByteMask16::find_high_bit_bytes(v).collect::<std::vec::Vec<_>>(), expect_hi);
+            }
+            else {
+                let v: [u8; 32] = b.try_into().unwrap();
+                assert_eq!(ByteMask32::find_zero_bytes(v).collect::<std::vec::Vec<_>>(), expect_eq(0));
+                assert_eq!(ByteMask32::find_equal_bytes(v, 9).collect::<std::vec::Vec<_>>(), expect_eq(9));
+                assert_eq!(ByteMask32::find_high_bit_bytes(v).collect::<std::vec::Vec<_>>(), expect_hi);
+            }
+        };
+        check(&bytes[..16]);
+        check(&bytes[16..]);
+        check(&bytes);
+
+        let m = ByteMask16::find_equal_bytes([7; 16], 7);
+        assert!(m.all());
+        assert!(m.not().none());
+        assert_eq!(!m, ByteMask16::NONE);
+        assert_eq!(m & ByteMask16::from_bits(0b101), ByteMask16::from_bits(0b101));
+        assert_eq!(ByteMask32::NONE | ByteMask32::from_bits(1 << 31), ByteMask32::from_bits(1 << 31));
+        assert_eq!(ByteMask32::from_bits(1 << 31).next(), Some(31));
+        assert!(ByteMask32::find_zero_bytes([1; 32]).none());
+    }
+}
@@ -283,3 +283,83 @@ impl<T: Hash + ?Sized> HashFn<T, u64> for FxHashFn {
     }
 }
 
+
+/// fxhash with a custom seed.
+///
+/// - the seed only changes the initial state.
+///   fxhash is easy to invert, so this does *not* protect
+///   against collision attacks. use `SipHashFn` for untrusted keys.
+#[derive(Clone, Copy, Debug)]
+pub struct SeededFxHashFn {
+    pub seed: u64,
+}
+
+impl SeededFxHashFn {
+    #[inline(always)]
+    pub const fn new(seed: u64) -> Self { Self { seed } }
+
+    /// seeded with `hash::process_seed`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn random() -> Self { Self { seed: crate::hash::process_seed().0 } }
+
+    /// folds the seed relative to the default,
+    /// so the default seed maps to `FxHasher32::DEFAULT_SEED`.
+    #[inline(always)]
+    fn seed32(&self) -> u32 {
+        let delta = self.seed ^ INI64;
+        (delta ^ (delta >> 32)) as u32 ^ INI32
+    }
+}
+
+impl Default for SeededFxHashFn {
+    #[inline(always)]
+    fn default() -> Self { Self { seed: INI64 } }
+}
+
+impl<T: Hash + ?Sized> HashFn<T, u32> for SeededFxHashFn {
+    #[inline(always)]
+    fn hash(&self, value: &T) -> u32 {
+        let mut hasher = FxHasher32::from_seed(self.seed32());
+        value.hash(&mut hasher);
+        hasher.hash
+    }
+}
+
+impl<T: Hash + ?Sized> HashFn<T, u64> for SeededFxHashFn {
+    #[inline(always)]
+    fn hash(&self, value: &T) -> u64 {
+        let mut hasher = FxHasher64::from_seed(self.seed);
+        value.hash(&mut hasher);
+        hasher.hash
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fx_seeded() {
+        let a = SeededFxHashFn::new(1);
+        let b = SeededFxHashFn::new(2);
+
+        let ha: u64 = a.hash("hello");
+        let hb: u64 = b.hash("hello");
+        assert_ne!(ha, hb);
+        assert_eq!(ha, HashFn::<str, u64>::hash(&a, "hello"));
+
+        let ha: u32 = a.hash("hello");
+        let hb: u32 = b.hash("hello");
+        assert_ne!(ha, hb);
+
+        // default seed matches `FxHashFn`.
+        let d = SeededFxHashFn::default();
+        assert_eq!(HashFn::<str, u64>::hash(&d, "hi"), fxhash64("hi"));
+        assert_eq!(HashFn::<str, u32>::hash(&d, "hi"), fxhash32("hi"));
+        assert_eq!(HashFn::<u32, u32>::hash(&d, &42), HashFn::<u32, u32>::hash(&FxHashFn, &42));
+    }
+}
+
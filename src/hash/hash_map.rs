@@ -1,6 +1,7 @@
 use crate::mem::{NonNull, PhantomData};
 use crate::borrow::Borrow;
-use crate::byte_mask::ByteMask8;
+use crate::ext::FromIn;
+use crate::key::Key;
 use crate::alloc::{Alloc, GlobalAlloc, Layout, cat_join, cat_next_mut};
 use crate::hash::{Hash, HashFn, fxhash::FxHashFn};
 
@@ -11,19 +12,98 @@ pub struct SlotIdx(pub u32);
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Hash32(pub u32);
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SlotIdx64(pub u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hash64(pub u64);
+
+
+/// the integer type of a `HashMap`'s hashes and slot indices.
+///
+/// - `u32`: the default. up to `u32::MAX` slots.
+/// - `u64`: up to `usize::MAX` slots. and the group index is
+///   computed from 64 bits, so huge maps have fewer collisions.
+///
+/// # safety:
+/// - `first_group` must return a value `< groups_num`.
+/// - `slot_idx` and `slot_usize` must round trip for values `<= Self::MAX_USIZE`.
+pub unsafe trait HashWidth: Key {
+    type Hash: Copy + Eq + crate::fmt::Debug;
+    type SlotIdx: Copy + Eq + crate::fmt::Debug;
+
+    fn hash(value: Self) -> Self::Hash;
+
+    /// the control byte for a used slot.
+    fn tag(hash: Self::Hash) -> u8;
 
-pub struct HashMap<K, V, A: Alloc = GlobalAlloc, H: HashFn<K, u32> = FxHashFn> {
+    /// the first group of the probe sequence.
+    fn first_group(hash: Self::Hash, groups_num: usize) -> usize;
+
+    fn slot_idx(idx: usize) -> Self::SlotIdx;
+    fn slot_usize(idx: Self::SlotIdx) -> usize;
+}
+
+unsafe impl HashWidth for u32 {
+    type Hash = Hash32;
+    type SlotIdx = SlotIdx;
+
+    #[inline(always)]
+    fn hash(value: Self) -> Hash32 { Hash32(value) }
+
+    #[inline(always)]
+    fn tag(hash: Hash32) -> u8 { (hash.0 & Group::TAG_MASK as u32) as u8 }
+
+    #[inline(always)]
+    fn first_group(hash: Hash32, groups_num: usize) -> usize {
+        ((hash.0 as u64 * groups_num as u64) >> 32) as usize
+    }
+
+    #[inline(always)]
+    fn slot_idx(idx: usize) -> SlotIdx { SlotIdx(idx as u32) }
+
+    #[inline(always)]
+    fn slot_usize(idx: SlotIdx) -> usize { idx.0 as usize }
+}
+
+unsafe impl HashWidth for u64 {
+    type Hash = Hash64;
+    type SlotIdx = SlotIdx64;
+
+    #[inline(always)]
+    fn hash(value: Self) -> Hash64 { Hash64(value) }
+
+    #[inline(always)]
+    fn tag(hash: Hash64) -> u8 { (hash.0 & Group::TAG_MASK as u64) as u8 }
+
+    #[inline(always)]
+    fn first_group(hash: Hash64, groups_num: usize) -> usize {
+        ((hash.0 as u128 * groups_num as u128) >> 64) as usize
+    }
+
+    #[inline(always)]
+    fn slot_idx(idx: usize) -> SlotIdx64 { SlotIdx64(idx as u64) }
+
+    #[inline(always)]
+    fn slot_usize(idx: SlotIdx64) -> usize { idx.0 as usize }
+}
+
+
+pub struct HashMap<K, V, A: Alloc = GlobalAlloc, H: HashFn<K, W> = FxHashFn, W: HashWidth = u32> {
     h: H,
     alloc: A,
 
-    groups_num: u32,
-    empty: u32,
-    used: u32,
+    groups_num: W,
+    empty: W,
+    used: W,
     groups_ptr: NonNull<Group>,
 
     phantom: PhantomData<(K, V)>,
 }
 
+/// a `HashMap` with 64 bit hashes and slot indices.
+pub type HashMap64<K, V, A = GlobalAlloc, H = FxHashFn> = HashMap<K, V, A, H, u64>;
+
 impl<K: Hash, V> HashMap<K, V, GlobalAlloc, FxHashFn> {
     #[inline(always)]
     pub const fn new() -> Self {
@@ -48,15 +128,41 @@ impl<K: Hash, V, A: Alloc> HashMap<K, V, A, FxHashFn> {
     }
 }
 
-impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
+// the 64 bit constructors have their own names.
+// with a second `new`, `HashMap::new()` couldn't infer the width.
+impl<K: Hash, V> HashMap<K, V, GlobalAlloc, FxHashFn, u64> {
+    #[inline(always)]
+    pub const fn new64() -> Self {
+        Self::new64_in(GlobalAlloc)
+    }
+
+    #[inline(always)]
+    pub fn with_cap64(cap: usize) -> Self {
+        Self::with_cap64_in(GlobalAlloc, cap)
+    }
+}
+
+impl<K: Hash, V, A: Alloc> HashMap<K, V, A, FxHashFn, u64> {
+    #[inline(always)]
+    pub const fn new64_in(alloc: A) -> Self {
+        Self::with_hash_fn_in(alloc, FxHashFn)
+    }
+
+    #[inline(always)]
+    pub fn with_cap64_in(alloc: A, cap: usize) -> Self {
+        Self::with_hash_and_cap_in(alloc, FxHashFn, cap)
+    }
+}
+
+impl<K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> HashMap<K, V, A, H, W> {
     #[inline]
     pub const fn with_hash_fn_in(alloc: A, h: H) -> Self {
         Self {
             h,
             alloc,
-            groups_num: 0,
-            empty: 0,
-            used: 0,
+            groups_num: W::MIN,
+            empty: W::MIN,
+            used: W::MIN,
             groups_ptr: NonNull::dangling(),
             phantom: PhantomData,
         }
@@ -64,7 +170,7 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
 
     pub fn with_hash_and_cap_in(alloc: A, h: H, cap: usize) -> Self {
         let mut this = Self::with_hash_fn_in(alloc, h);
-        let num_groups = num_groups_for_cap(cap).unwrap();
+        let num_groups = num_groups_for_cap(cap, W::MAX_USIZE).unwrap();
         this.resize(Some(num_groups));
         return this;
     }
@@ -81,148 +187,281 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
 
     #[inline]
     pub fn size(&self) -> usize {
-        self.groups_num as usize * Group::WIDTH
+        self.groups_num() * Group::WIDTH
     }
 
     #[inline]
     pub fn cap(&self) -> usize {
-        self.groups_num as usize * EMPTY_PER_GROUP as usize
+        self.groups_num() * EMPTY_PER_GROUP
     }
 
     #[inline]
     pub fn resident(&self) -> usize {
-        self.cap() - self.empty as usize
+        self.cap() - self.empty.usize()
     }
 
     #[inline]
     pub fn len(&self) -> usize {
-        self.used as usize
+        self.used.usize()
+    }
+
+    #[inline(always)]
+    fn groups_num(&self) -> usize {
+        self.groups_num.usize()
+    }
+
+    #[inline(always)]
+    fn set_empty(&mut self, empty: usize) {
+        self.empty = width(empty);
+    }
+
+    #[inline(always)]
+    fn set_used(&mut self, used: usize) {
+        self.used = width(used);
     }
 
 
     pub fn reserve(&mut self, min_cap: usize) {
-        let min_groups_num = num_groups_for_cap(min_cap).unwrap();
-        if min_groups_num > self.groups_num {
+        let min_groups_num = num_groups_for_cap(min_cap, W::MAX_USIZE).unwrap();
+        if min_groups_num > self.groups_num() {
             self.resize(Some(min_groups_num));
         }
     }
 
+    /// makes sure, there's room for one more entry.
+    ///
+    /// - if tombstones use up most of the capacity,
+    ///   the table is rehashed in place instead of growing.
     #[inline]
     pub fn reserve_for_insert(&mut self) {
-        if self.empty == 0 {
-            self.resize(None);
+        if self.empty == W::MIN {
+            if self.groups_num() != 0 && self.len() <= self.cap() / 2 {
+                self.rehash_in_place();
+            }
+            else {
+                self.resize(None);
+            }
         }
     }
 
+    /// rehashes all entries without reallocating.
+    ///
+    /// - turns all tombstones back into fresh slots,
+    ///   so `resident() == len()` afterwards.
+    /// - invalidates all slot indices.
+    /// - `O(size)`.
+    pub fn rehash_in_place(&mut self) { unsafe {
+        let groups_num = self.groups_num();
+        if groups_num == 0 { return }
+
+        // used -> tombstone, which we use as "needs rehash".
+        // tombstone -> fresh.
+        for group_idx in 0..groups_num {
+            let group = &mut *self.groups_ptr.as_ptr().add(group_idx);
+            let used = group.match_used();
+            *group = Group::fresh();
+            for i in used {
+                group.set(i, Group::TOMBSTONE);
+            }
+        }
+
+        // entries only move to earlier groups in their probe sequence.
+        // the groups they skip over are fully used and stay that way,
+        // so turning pending slots into fresh ones doesn't break lookups.
+        for idx in 0..self.size() {
+            let group = Self::group_ptr(self.groups_ptr, idx);
+            if (*group).get(idx) != Group::TOMBSTONE {
+                continue;
+            }
+
+            let slot = Self::slot_ptr(groups_num, self.groups_ptr, idx);
+            loop {
+                let hash = self.hash(&(*slot).0);
+
+                let target = {
+                    let mut group_idx = W::first_group(hash, groups_num);
+                    loop {
+                        let group = &*self.groups_ptr.as_ptr().add(group_idx);
+
+                        let mut free = group.match_fresh() | group.match_tomb();
+                        if let Some(i) = free.next() {
+                            break Group::WIDTH*group_idx + i;
+                        }
+
+                        group_idx += 1;
+                        if group_idx == groups_num {
+                            group_idx = 0;
+                        }
+                    }
+                };
+
+                // already in the right group.
+                if target / Group::WIDTH == idx / Group::WIDTH {
+                    (*group).use_entry(idx, W::tag(hash));
+                    break;
+                }
+
+                let target_group = &mut *Self::group_ptr(self.groups_ptr, target);
+                let target_slot = Self::slot_ptr(groups_num, self.groups_ptr, target);
+
+                if target_group.is_fresh(target) {
+                    crate::asan::unpoison_ptr(target_slot);
+                    target_slot.write(slot.read());
+                    crate::asan::poison_ptr(slot);
+
+                    target_group.use_entry(target, W::tag(hash));
+                    (*group).set(idx, Group::FRESH);
+                    break;
+                }
+                else {
+                    // swap with the pending entry
+                    // and rehash that one next.
+                    core::ptr::swap_nonoverlapping(slot, target_slot, 1);
+                    target_group.use_entry(target, W::tag(hash));
+                }
+            }
+        }
+
+        self.set_empty(EMPTY_PER_GROUP * groups_num - self.len());
+    }}
+
 
     #[inline(always)]
-    pub fn hash<Q>(&self, k: &Q) -> Hash32
-    where Q: ?Sized, K: Borrow<Q>, H: HashFn<Q, u32> {
-        Hash32(self.h.hash(k))
+    pub fn hash<Q>(&self, k: &Q) -> W::Hash
+    where Q: ?Sized, K: Borrow<Q>, H: HashFn<Q, W> {
+        W::hash(self.h.hash(k))
     }
 
 
     #[inline(always)]
-    pub fn lookup<Q>(&self, q: &Q, hash: Hash32) -> (bool, SlotIdx)
+    pub fn lookup<Q>(&self, q: &Q, hash: W::Hash) -> (bool, W::SlotIdx)
     where Q: ?Sized + Eq, K: Borrow<Q> {
         return self.lookup_cmp(hash, move |k| q == k.borrow());
     }
 
-    pub fn lookup_cmp(&self, hash: Hash32, cmp: impl Fn(&K) -> bool) -> (bool, SlotIdx) {
-        if self.groups_num == 0 {
-            return (false, SlotIdx(0));
+    pub fn lookup_cmp(&self, hash: W::Hash, cmp: impl Fn(&K) -> bool) -> (bool, W::SlotIdx) {
+        let groups_num = self.groups_num();
+        if groups_num == 0 {
+            return (false, W::slot_idx(0));
         }
 
-        let slots = Self::slots_ptr(self.groups_num, self.groups_ptr);
+        let slots = Self::slots_ptr(groups_num, self.groups_ptr);
+        let tag = W::tag(hash);
 
         let mut tomb = None;
 
-        let mut group_idx = Group::first_idx(hash.0, self.groups_num);
+        let mut group_idx = W::first_group(hash, groups_num);
         loop { unsafe {
             let group = &*self.groups_ptr.as_ptr().add(group_idx);
             let group_slots = slots.add(Group::WIDTH*group_idx);
 
-            for i in group.match_hash(hash) {
+            for i in group.match_tag(tag) {
                 let slot = &*group_slots.add(i);
                 if cmp(&slot.0) {
-                    return (true, SlotIdx((Group::WIDTH*group_idx + i) as u32));
+                    return (true, W::slot_idx(Group::WIDTH*group_idx + i));
                 }
             }
 
             if tomb.is_none() {
                 if let Some(i) = group.match_tomb().next() {
-                    tomb = Some(SlotIdx((Group::WIDTH*group_idx + i) as u32));
+                    tomb = Some(Group::WIDTH*group_idx + i);
                 }
             }
 
             if let Some(i) = group.match_fresh().next() {
-                let idx = tomb.unwrap_or(SlotIdx((Group::WIDTH*group_idx + i) as u32));
-                return (false, idx);
+                let idx = tomb.unwrap_or(Group::WIDTH*group_idx + i);
+                return (false, W::slot_idx(idx));
             }
 
             group_idx += 1;
-            if group_idx == self.groups_num as usize {
+            if group_idx == groups_num {
                 group_idx = 0;
             }
         }}
     }
 
     #[inline]
-    pub fn lookup_for_insert<Q>(&mut self, k: &Q, hash: Hash32) -> (bool, SlotIdx)
+    pub fn lookup_for_insert<Q>(&mut self, k: &Q, hash: W::Hash) -> (bool, W::SlotIdx)
     where Q: ?Sized + Eq, K: Borrow<Q> {
         self.reserve_for_insert();
         self.lookup(k, hash)
     }
 
 
-    pub fn entry<Q>(&self, k: &Q) -> (bool, SlotIdx)
-    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+    pub fn entry<Q>(&self, k: &Q) -> (bool, W::SlotIdx)
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, W> {
         self.lookup(k, self.hash(k))
     }
 
     #[inline]
-    pub fn entry_for_insert<Q>(&mut self, k: &Q) -> (bool, SlotIdx)
-    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+    pub fn entry_for_insert<Q>(&mut self, k: &Q) -> (bool, W::SlotIdx)
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, W> {
         self.lookup_for_insert(k, self.hash(k))
     }
 
 
+    /// the entry for `k`, for in-place manipulation.
+    ///
+    /// - single probe. reserves room for an insert, like `insert`.
+    /// - `entry` is the low level version, that returns a slot index.
     #[inline]
-    pub fn slot_present(&self, idx: SlotIdx) -> bool {
-        return (idx.0 as usize) < self.size()
+    pub fn kentry(&mut self, k: K) -> Entry<'_, K, V, A, H, W>
+    where K: Eq {
+        let hash = self.hash(&k);
+        return self.kentry_with_hash(k, hash);
+    }
+
+    /// `kentry`, for a precomputed `hash`.
+    ///
+    /// - `hash` must be `self.hash(&k)`, or the entry may be missed.
+    #[inline]
+    pub fn kentry_with_hash(&mut self, k: K, hash: W::Hash) -> Entry<'_, K, V, A, H, W>
+    where K: Eq {
+        let (present, idx) = self.lookup_for_insert(&k, hash);
+        if present {
+            return Entry::Occupied(OccupiedEntry { map: self, idx });
+        }
+        return Entry::Vacant(VacantEntry { map: self, idx, hash, key: k });
+    }
+
+
+    #[inline]
+    pub fn slot_present(&self, idx: W::SlotIdx) -> bool {
+        let idx = W::slot_usize(idx);
+        return idx < self.size()
             && unsafe { (&*Self::group_ptr(self.groups_ptr, idx)).is_used(idx) };
     }
 
     #[inline]
-    pub fn slot(&self, idx: SlotIdx) -> (&K, &V) {
+    pub fn slot(&self, idx: W::SlotIdx) -> (&K, &V) {
         assert!(self.slot_present(idx));
         return unsafe { self.slot_unck(idx) };
     }
 
     #[inline]
-    pub unsafe fn slot_unck(&self, idx: SlotIdx) -> (&K, &V) { unsafe {
+    pub unsafe fn slot_unck(&self, idx: W::SlotIdx) -> (&K, &V) { unsafe {
         debug_assert!(self.slot_present(idx));
-        let (k, v) = &*Self::slot_ptr(self.groups_num, self.groups_ptr, idx);
+        let (k, v) = &*Self::slot_ptr(self.groups_num(), self.groups_ptr, W::slot_usize(idx));
         return (k, v);
     }}
 
     #[inline]
-    pub fn slot_mut(&mut self, idx: SlotIdx) -> (&K, &mut V) {
+    pub fn slot_mut(&mut self, idx: W::SlotIdx) -> (&K, &mut V) {
         assert!(self.slot_present(idx));
         return unsafe { self.slot_unck_mut(idx) };
     }
 
     #[inline]
-    pub unsafe fn slot_unck_mut(&mut self, idx: SlotIdx) -> (&K, &mut V) { unsafe {
+    pub unsafe fn slot_unck_mut(&mut self, idx: W::SlotIdx) -> (&K, &mut V) { unsafe {
         debug_assert!(self.slot_present(idx));
-        let (k, v) = &mut *Self::slot_ptr(self.groups_num, self.groups_ptr, idx);
+        let (k, v) = &mut *Self::slot_ptr(self.groups_num(), self.groups_ptr, W::slot_usize(idx));
         return (k, v);
     }}
 
 
     #[inline]
     pub fn get<Q>(&self, k: &Q) -> Option<&V>
-    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, W> {
         let (present, idx) = self.entry(k);
         if present {
             Some(unsafe { self.slot_unck(idx).1 })
@@ -233,7 +472,7 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
 
     #[inline]
     pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
-    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, W> {
         let (present, idx) = self.entry(k);
         if present {
             Some(unsafe { self.slot_unck_mut(idx).1 })
@@ -266,16 +505,17 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
     }
 
     #[inline]
-    pub fn insert_at(&mut self, idx: SlotIdx, hash: Hash32, k: K, v: V) -> Option<(K, V)> {
-        assert!((idx.0 as usize) < self.size() && self.empty > 0);
+    pub fn insert_at(&mut self, idx: W::SlotIdx, hash: W::Hash, k: K, v: V) -> Option<(K, V)> {
+        assert!(W::slot_usize(idx) < self.size() && self.empty != W::MIN);
         return unsafe { self.insert_at_unck(idx, hash, k, v) };
     }
 
     #[inline]
-    pub unsafe fn insert_at_unck(&mut self, idx: SlotIdx, hash: Hash32, k: K, v: V) -> Option<(K, V)> { unsafe {
-        debug_assert!((idx.0 as usize) < self.size() && self.empty > 0);
+    pub unsafe fn insert_at_unck(&mut self, idx: W::SlotIdx, hash: W::Hash, k: K, v: V) -> Option<(K, V)> { unsafe {
+        let idx = W::slot_usize(idx);
+        debug_assert!(idx < self.size() && self.empty != W::MIN);
 
-        let slot = Self::slot_ptr(self.groups_num, self.groups_ptr, idx);
+        let slot = Self::slot_ptr(self.groups_num(), self.groups_ptr, idx);
         let group = &mut *Self::group_ptr(self.groups_ptr, idx);
 
         let was_used = group.is_used(idx);
@@ -283,14 +523,15 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
             if was_used { Some(slot.read()) }
             else { None };
 
-        self.empty -= group.is_fresh(idx) as u32;
+        let empty = self.empty.usize() - group.is_fresh(idx) as usize;
+        self.set_empty(empty);
 
-        group.use_entry(idx, hash);
+        group.use_entry(idx, W::tag(hash));
 
         crate::asan::unpoison_ptr(slot);
         slot.write((k, v));
 
-        self.used += !was_used as u32;
+        self.set_used(self.len() + !was_used as usize);
 
         return result;
     }}
@@ -298,7 +539,7 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
 
     pub fn remove<Q>(&mut self, k: &Q) -> Option<(K, V)>
     where Q: ?Sized + Eq,
-          H: HashFn<Q, u32>,
+          H: HashFn<Q, W>,
           K: Borrow<Q>
     {
         let (present, idx) = self.lookup(k, self.hash(k));
@@ -307,34 +548,37 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
     }
 
     #[inline]
-    pub fn remove_at(&mut self, idx: SlotIdx) -> (K, V) {
+    pub fn remove_at(&mut self, idx: W::SlotIdx) -> (K, V) {
         assert!(self.slot_present(idx));
         return unsafe { self.remove_at_unck(idx) };
     }
 
     #[inline]
-    pub unsafe fn remove_at_unck(&mut self, idx: SlotIdx) -> (K, V) { unsafe {
-        debug_assert!((idx.0 as usize) < self.size());
+    pub unsafe fn remove_at_unck(&mut self, idx: W::SlotIdx) -> (K, V) { unsafe {
+        let idx = W::slot_usize(idx);
+        debug_assert!(idx < self.size());
 
-        let slot = Self::slot_ptr(self.groups_num, self.groups_ptr, idx);
+        let slot = Self::slot_ptr(self.groups_num(), self.groups_ptr, idx);
         let group = &mut *Self::group_ptr(self.groups_ptr, idx);
         debug_assert!(group.is_used(idx));
 
         let result = slot.read();
         crate::asan::poison_ptr(slot);
 
-        self.empty += group.free_entry(idx);
-        self.used -= 1;
+        let empty = self.empty.usize() + group.free_entry(idx) as usize;
+        self.set_empty(empty);
+        self.set_used(self.len() - 1);
 
         return result;
     }}
 
 
     pub fn clear(&mut self) { unsafe {
-        if self.used == 0 { return }
+        if self.used == W::MIN { return }
 
-        let slots = Self::slots_ptr(self.groups_num, self.groups_ptr);
-        for group_idx in 0..self.groups_num as usize {
+        let groups_num = self.groups_num();
+        let slots = Self::slots_ptr(groups_num, self.groups_ptr);
+        for group_idx in 0..groups_num {
             let group = &mut *self.groups_ptr.as_ptr().add(group_idx);
             let group_slots = slots.add(Group::WIDTH*group_idx);
 
@@ -344,19 +588,21 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
 
             *group = Group::fresh();
         }
-        crate::asan::poison_ptr_len(slots, Group::WIDTH.wrapping_mul(self.groups_num as usize));
+        crate::asan::poison_ptr_len(slots, Group::WIDTH.wrapping_mul(groups_num));
 
-        self.empty = EMPTY_PER_GROUP * self.groups_num;
-        self.used = 0;
+        self.set_empty(EMPTY_PER_GROUP * groups_num);
+        self.set_used(0);
     }}
 
 
-    pub fn clone_in<A2>(&self, alloc: A2) -> HashMap<K, V, A2, H>
+    pub fn clone_in<A2>(&self, alloc: A2) -> HashMap<K, V, A2, H, W>
     where K: Clone, V: Clone, H: Clone, A2: Alloc
     { unsafe {
+        let groups_num = self.groups_num();
+
         // allocate uninitialized hash map with same capacity.
         let mut result = {
-            let layout = Self::layout(self.groups_num).unwrap_unchecked();
+            let layout = Self::layout(groups_num).unwrap_unchecked();
 
             let data = alloc.alloc(layout).unwrap();
 
@@ -365,16 +611,16 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
                 alloc,
                 groups_num: self.groups_num,
                 empty: self.empty,
-                used: 0,
+                used: W::MIN,
                 groups_ptr: data.cast(),
                 phantom: PhantomData,
             }
         };
 
         // clone slots.
-        let src_slots = Self::slots_ptr(self.groups_num, self.groups_ptr);
-        let dst_slots = Self::slots_ptr(result.groups_num, result.groups_ptr);
-        for group_idx in 0..self.groups_num as usize {
+        let src_slots = Self::slots_ptr(groups_num, self.groups_ptr);
+        let dst_slots = Self::slots_ptr(groups_num, result.groups_ptr);
+        for group_idx in 0..groups_num {
             let group = &*self.groups_ptr.as_ptr().add(group_idx);
             let src_group_slots = src_slots.add(Group::WIDTH*group_idx);
             let dst_group_slots = dst_slots.add(Group::WIDTH*group_idx);
@@ -390,7 +636,7 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
         core::ptr::copy_nonoverlapping(
             self.groups_ptr.as_ptr(),
             result.groups_ptr.as_ptr(),
-            self.groups_num as usize);
+            groups_num);
 
         result.used = self.used;
 
@@ -402,10 +648,10 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
     pub fn iter(&self) -> Iter<K, V> {
         Iter {
             groups: self.groups_ptr.as_ptr(),
-            slots: Self::slots_ptr(self.groups_num, self.groups_ptr),
-            rem: self.used,
-            slots_num: self.groups_num * Group::WIDTH as u32,
-            slot_idx: SlotIdx(0),
+            slots: Self::slots_ptr(self.groups_num(), self.groups_ptr),
+            rem: self.len(),
+            slots_num: self.size(),
+            slot_idx: 0,
             phantom: PhantomData,
         }
     }
@@ -414,38 +660,90 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
     pub fn iter_mut(&mut self) -> IterMut<K, V> {
         IterMut {
             groups: self.groups_ptr.as_ptr(),
-            slots: Self::slots_ptr(self.groups_num, self.groups_ptr),
-            rem: self.used,
-            slots_num: self.groups_num * Group::WIDTH as u32,
-            slot_idx: SlotIdx(0),
+            slots: Self::slots_ptr(self.groups_num(), self.groups_ptr),
+            rem: self.len(),
+            slots_num: self.size(),
+            slot_idx: 0,
             phantom: PhantomData,
         }
     }
 
 
-    fn resize(&mut self, new_groups_num: Option<u32>) {
+    /// keeps only the entries, for which `f` returns `true`.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        let mut from = 0;
+        while let Some(idx) = next_used_slot(self.groups_ptr.as_ptr(), self.groups_num(), from) {
+            from = idx + 1;
+
+            let (k, v) = unsafe { &mut *Self::slot_ptr(self.groups_num(), self.groups_ptr, idx) };
+            if !f(k, v) {
+                drop(unsafe { self.remove_at_unck(W::slot_idx(idx)) });
+            }
+        }
+    }
+
+    /// removes all entries and returns them in an iterator.
+    ///
+    /// - entries, that aren't consumed, are dropped with the iterator.
+    /// - keeps the allocation.
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, K, V, A, H, W> {
+        Drain { map: self, slot_idx: 0 }
+    }
+
+    /// removes the entries, for which `f` returns `true`, and returns them in an iterator.
+    ///
+    /// - entries, that aren't visited, are kept.
+    #[inline]
+    pub fn extract_if<F: FnMut(&K, &mut V) -> bool>(&mut self, f: F) -> ExtractIf<'_, K, V, A, H, F, W> {
+        ExtractIf { map: self, slot_idx: 0, f }
+    }
+
+    /// marks all slots fresh.
+    ///
+    /// # safety:
+    /// - all entries must have been moved out or dropped.
+    unsafe fn reset_groups(&mut self) {
+        let groups_num = self.groups_num();
+        if groups_num == 0 { return }
+
+        for group_idx in 0..groups_num {
+            unsafe { *self.groups_ptr.as_ptr().add(group_idx) = Group::fresh() }
+        }
+        let slots = Self::slots_ptr(groups_num, self.groups_ptr);
+        crate::asan::poison_ptr_len(slots, Group::WIDTH.wrapping_mul(groups_num));
+
+        self.set_empty(EMPTY_PER_GROUP * groups_num);
+        self.set_used(0);
+    }
+
+
+    fn resize(&mut self, new_groups_num: Option<usize>) {
         let new_groups_num = new_groups_num.unwrap_or_else(||
-            self.groups_num.checked_mul(2).unwrap().max(1));
+            self.groups_num().checked_mul(2).unwrap().max(1));
+
+        // slot indices must fit into `W`.
+        assert!(new_groups_num.checked_mul(Group::WIDTH).unwrap() <= W::MAX_USIZE);
 
         let layout = Self::layout(new_groups_num).unwrap();
         let data = self.alloc.alloc(layout).unwrap();
 
         let new_groups_ptr: NonNull<Group> = data.cast();
-        for i in 0..new_groups_num as usize {
+        for i in 0..new_groups_num {
             unsafe { new_groups_ptr.as_ptr().add(i).write(Group::fresh()) }
         }
 
         let new_slots = Self::slots_ptr(new_groups_num, new_groups_ptr);
-        crate::asan::poison_ptr_len(new_slots, Group::WIDTH.wrapping_mul(new_groups_num as usize));
+        crate::asan::poison_ptr_len(new_slots, Group::WIDTH.wrapping_mul(new_groups_num));
 
-        let old_groups_num = self.groups_num;
-        let old_used = self.used;
+        let old_groups_num = self.groups_num();
+        let old_used = self.len();
         let old_groups_ptr = self.groups_ptr;
         let old_slots = Self::slots_ptr(old_groups_num, old_groups_ptr);
 
-        self.groups_num = new_groups_num;
-        self.empty = EMPTY_PER_GROUP * new_groups_num;
-        self.used  = 0;
+        self.groups_num = width(new_groups_num);
+        self.set_empty(EMPTY_PER_GROUP * new_groups_num);
+        self.set_used(0);
         self.groups_ptr = new_groups_ptr;
 
         if old_groups_num == 0 {
@@ -453,43 +751,45 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
             return;
         }
 
-        for group_idx in 0..old_groups_num as usize { unsafe {
+        let mut empty = self.empty.usize();
+        for group_idx in 0..old_groups_num { unsafe {
             let group = &*old_groups_ptr.as_ptr().add(group_idx);
             let group_slots = old_slots.add(Group::WIDTH*group_idx);
 
             for i in group.match_used() {
-                assert!(self.empty > 0);
+                assert!(empty > 0);
 
                 let slot = group_slots.add(i).read();
 
                 let hash = self.hash(&slot.0);
 
                 let idx = {
-                    let mut group_idx = Group::first_idx(hash.0, self.groups_num);
+                    let mut group_idx = W::first_group(hash, new_groups_num);
                     loop {
                         let group = &*self.groups_ptr.as_ptr().add(group_idx);
 
                         if let Some(i) = group.match_fresh().next() {
-                            break SlotIdx((Group::WIDTH*group_idx + i) as u32);
+                            break Group::WIDTH*group_idx + i;
                         }
 
                         group_idx += 1;
-                        if group_idx == self.groups_num as usize {
+                        if group_idx == new_groups_num {
                             group_idx = 0;
                         }
                     }
                 };
 
-                let slot_ptr = new_slots.add(idx.0 as usize);
+                let slot_ptr = new_slots.add(idx);
                 crate::asan::unpoison_ptr(slot_ptr);
                 slot_ptr.write(slot);
 
-                (&mut *Self::group_ptr(new_groups_ptr, idx)).use_entry(idx, hash);
+                (&mut *Self::group_ptr(new_groups_ptr, idx)).use_entry(idx, W::tag(hash));
 
-                self.empty -= 1;
-                self.used  += 1;
+                empty -= 1;
             }
         }}
+        self.set_empty(empty);
+        self.set_used(old_used);
 
         unsafe {
             let layout = Self::layout(old_groups_num).unwrap_unchecked();
@@ -499,8 +799,7 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
 
 
     #[inline]
-    fn layout(num_groups: u32) -> Option<Layout> {
-        let num_groups: usize = num_groups.try_into().expect("unreachable");
+    fn layout(num_groups: usize) -> Option<Layout> {
         let num_slots = num_groups.checked_mul(Group::WIDTH)?;
         cat_join(
             Layout::array::<Group>(num_groups).ok()?,
@@ -508,39 +807,55 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> HashMap<K, V, A, H> {
     }
 
     #[inline]
-    fn slots_ptr(groups_num: u32, groups_ptr: NonNull<Group>) -> *mut (K, V) {
-        unsafe { cat_next_mut(groups_ptr.as_ptr(), groups_num as usize) }
+    fn slots_ptr(groups_num: usize, groups_ptr: NonNull<Group>) -> *mut (K, V) {
+        unsafe { cat_next_mut(groups_ptr.as_ptr(), groups_num) }
     }
 
     #[inline]
-    fn slot_ptr(groups_num: u32, groups_ptr: NonNull<Group>, idx: SlotIdx) -> *mut (K, V) {
-        unsafe { Self::slots_ptr(groups_num, groups_ptr).add(idx.0 as usize) }
+    fn slot_ptr(groups_num: usize, groups_ptr: NonNull<Group>, idx: usize) -> *mut (K, V) {
+        unsafe { Self::slots_ptr(groups_num, groups_ptr).add(idx) }
     }
 
     #[inline]
-    fn group_ptr(groups_ptr: NonNull<Group>, idx: SlotIdx) -> *mut Group {
-        unsafe { groups_ptr.as_ptr().add(idx.0 as usize / Group::WIDTH) }
+    fn group_ptr(groups_ptr: NonNull<Group>, idx: usize) -> *mut Group {
+        unsafe { groups_ptr.as_ptr().add(idx / Group::WIDTH) }
     }
 }
 
 
-impl<K: Clone, V: Clone, A: Alloc + Clone, H: HashFn<K, u32> + Clone> Clone for HashMap<K, V, A, H> {
+/// converts a count, that's known to fit.
+#[inline(always)]
+fn width<W: HashWidth>(value: usize) -> W {
+    debug_assert!(value <= W::MAX_USIZE);
+    unsafe { W::from_usize_unck(value) }
+}
+
+
+unsafe impl<K: Send, V: Send, A: Alloc + Send, H: HashFn<K, W> + Send, W: HashWidth> Send for HashMap<K, V, A, H, W> {}
+unsafe impl<K: Sync, V: Sync, A: Alloc + Sync, H: HashFn<K, W> + Sync, W: HashWidth> Sync for HashMap<K, V, A, H, W> {}
+
+unsafe impl<K: Send, V: Send, A: Alloc + Send> Send for IntoIter<K, V, A> {}
+unsafe impl<K: Sync, V: Sync, A: Alloc + Sync> Sync for IntoIter<K, V, A> {}
+
+
+impl<K: Clone, V: Clone, A: Alloc + Clone, H: HashFn<K, W> + Clone, W: HashWidth> Clone for HashMap<K, V, A, H, W> {
     fn clone(&self) -> Self {
         return self.clone_in(self.alloc.clone());
     }
 }
 
 
-impl<K, V, A: Alloc, H: HashFn<K, u32>> Drop for HashMap<K, V, A, H> {
+impl<K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> Drop for HashMap<K, V, A, H, W> {
     fn drop(&mut self) { unsafe {
-        if self.groups_num == 0 { return }
+        let groups_num = self.groups_num();
+        if groups_num == 0 { return }
 
         // nb: we don't use clear because that writes to the groups.
         // we assume most users have non-drop entries, so this entire loop
         // should get DCE'd.
-        if self.used != 0 {
-            let slots = Self::slots_ptr(self.groups_num, self.groups_ptr);
-            for group_idx in 0..self.groups_num as usize {
+        if self.used != W::MIN {
+            let slots = Self::slots_ptr(groups_num, self.groups_ptr);
+            for group_idx in 0..groups_num {
                 let group = &*self.groups_ptr.as_ptr().add(group_idx);
                 let group_slots = slots.add(Group::WIDTH*group_idx);
 
@@ -550,13 +865,13 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> Drop for HashMap<K, V, A, H> {
             }
         }
 
-        let layout = Self::layout(self.groups_num).unwrap_unchecked();
+        let layout = Self::layout(groups_num).unwrap_unchecked();
         self.alloc.free(self.groups_ptr.cast(), layout);
     }}
 }
 
 
-impl<K, V, A: Alloc + Default, H: HashFn<K, u32> + Default> Default for HashMap<K, V, A, H> {
+impl<K, V, A: Alloc + Default, H: HashFn<K, W> + Default, W: HashWidth> Default for HashMap<K, V, A, H, W> {
     #[inline]
     fn default() -> Self {
         Self::with_hash_fn_in(A::default(), H::default())
@@ -564,15 +879,15 @@ impl<K, V, A: Alloc + Default, H: HashFn<K, u32> + Default> Default for HashMap<
 }
 
 
-impl<K: crate::fmt::Debug, V: crate::fmt::Debug, A: Alloc, H: HashFn<K, u32>> crate::fmt::Debug for HashMap<K, V, A, H> {
+impl<K: crate::fmt::Debug, V: crate::fmt::Debug, A: Alloc, H: HashFn<K, W>, W: HashWidth> crate::fmt::Debug for HashMap<K, V, A, H, W> {
     fn fmt(&self, f: &mut crate::fmt::Formatter) -> crate::fmt::Result {
         f.debug_map().entries(self.iter()).finish()
     }
 }
 
 
-impl<Q, K, V, A: Alloc + Default, H: HashFn<K, u32> + Default> crate::ops::Index<&Q> for HashMap<K, V, A, H>
-where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+impl<Q, K, V, A: Alloc + Default, H: HashFn<K, W> + Default, W: HashWidth> crate::ops::Index<&Q> for HashMap<K, V, A, H, W>
+where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, W> {
     type Output = V;
 
     #[inline]
@@ -581,8 +896,8 @@ where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
     }
 }
 
-impl<Q, K, V, A: Alloc + Default, H: HashFn<K, u32> + Default> crate::ops::IndexMut<&Q> for HashMap<K, V, A, H>
-where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+impl<Q, K, V, A: Alloc + Default, H: HashFn<K, W> + Default, W: HashWidth> crate::ops::IndexMut<&Q> for HashMap<K, V, A, H, W>
+where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, W> {
     #[inline]
     fn index_mut(&mut self, k: &Q) -> &mut Self::Output {
         self.get_mut(k).unwrap()
@@ -590,12 +905,333 @@ where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
 }
 
 
+impl<K: Hash + Eq, V, A: Alloc, I: Iterator<Item = (K, V)>, W: HashWidth> FromIn<I, A> for HashMap<K, V, A, FxHashFn, W>
+where FxHashFn: HashFn<K, W> {
+    #[inline]
+    fn from_in(alloc: A, iter: I) -> Self {
+        let mut result = HashMap::with_hash_fn_in(alloc, FxHashFn);
+        result.extend(iter);
+        return result;
+    }
+}
+
+impl<K: Hash + Eq, V, W: HashWidth> FromIterator<(K, V)> for HashMap<K, V, GlobalAlloc, FxHashFn, W>
+where FxHashFn: HashFn<K, W> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self::from_in(GlobalAlloc, iter.into_iter())
+    }
+}
+
+impl<K: Eq, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> Extend<(K, V)> for HashMap<K, V, A, H, W> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+
+        // the iterator may contain duplicates.
+        let (min_len, _) = iter.size_hint();
+        self.reserve(self.len() + min_len);
+
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> IntoIterator for HashMap<K, V, A, H, W> {
+    type IntoIter = IntoIter<K, V, A>;
+    type Item = (K, V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        let map = crate::mem::ManuallyDrop::new(self);
+        drop(unsafe { crate::ptr::read(&map.h) });
+        return IntoIter {
+            groups: map.groups_ptr,
+            slots: Self::slots_ptr(map.groups_num(), map.groups_ptr),
+            groups_num: map.groups_num(),
+            rem: map.len(),
+            slot_idx: 0,
+            alloc: unsafe { crate::ptr::read(&map.alloc) },
+            layout: Self::layout(map.groups_num()),
+        };
+    }
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> IntoIterator for &'a HashMap<K, V, A, H, W> {
+    type IntoIter = Iter<'a, K, V>;
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        return self.iter();
+    }
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> IntoIterator for &'a mut HashMap<K, V, A, H, W> {
+    type IntoIter = IterMut<'a, K, V>;
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        return self.iter_mut();
+    }
+}
+
+
+pub struct IntoIter<K, V, A: Alloc> {
+    groups: NonNull<Group>,
+    slots: *mut (K, V),
+    groups_num: usize,
+    rem: usize,
+    slot_idx: usize,
+    alloc: A,
+    // `None` if there is no allocation.
+    layout: Option<Layout>,
+}
+
+impl<K, V, A: Alloc> Iterator for IntoIter<K, V, A> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = next_used_slot(self.groups.as_ptr(), self.groups_num, self.slot_idx)?;
+        self.slot_idx = idx + 1;
+        self.rem -= 1;
+        return Some(unsafe { self.slots.add(idx).read() });
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.rem, Some(self.rem))
+    }
+}
+
+impl<K, V, A: Alloc> Drop for IntoIter<K, V, A> {
+    fn drop(&mut self) {
+        if crate::mem::needs_drop::<(K, V)>() {
+            for _ in &mut *self {}
+        }
+
+        if self.groups_num != 0 {
+            let layout = self.layout.unwrap();
+            unsafe { self.alloc.free(self.groups.cast(), layout) }
+        }
+    }
+}
+
+
+pub struct Drain<'a, K, V, A: Alloc = GlobalAlloc, H: HashFn<K, W> = FxHashFn, W: HashWidth = u32> {
+    map: &'a mut HashMap<K, V, A, H, W>,
+    slot_idx: usize,
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> Iterator for Drain<'a, K, V, A, H, W> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let map = &mut *self.map;
+        let idx = next_used_slot(map.groups_ptr.as_ptr(), map.groups_num(), self.slot_idx)?;
+        self.slot_idx = idx + 1;
+        return Some(unsafe { map.remove_at_unck(W::slot_idx(idx)) });
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.map.len(), Some(self.map.len()))
+    }
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> Drop for Drain<'a, K, V, A, H, W> {
+    fn drop(&mut self) {
+        for _ in &mut *self {}
+
+        // no tombstones.
+        unsafe { self.map.reset_groups() }
+    }
+}
+
+
+pub struct ExtractIf<'a, K, V, A: Alloc, H: HashFn<K, W>, F: FnMut(&K, &mut V) -> bool, W: HashWidth = u32> {
+    map: &'a mut HashMap<K, V, A, H, W>,
+    slot_idx: usize,
+    f: F,
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, F: FnMut(&K, &mut V) -> bool, W: HashWidth> Iterator for ExtractIf<'a, K, V, A, H, F, W> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let map = &mut *self.map;
+        while let Some(idx) = next_used_slot(map.groups_ptr.as_ptr(), map.groups_num(), self.slot_idx) {
+            self.slot_idx = idx + 1;
+
+            let (k, v) = unsafe { &mut *HashMap::<K, V, A, H, W>::slot_ptr(map.groups_num(), map.groups_ptr, idx) };
+            if (self.f)(k, v) {
+                return Some(unsafe { map.remove_at_unck(W::slot_idx(idx)) });
+            }
+        }
+        return None;
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.map.len()))
+    }
+}
+
+
+pub enum Entry<'a, K, V, A: Alloc = GlobalAlloc, H: HashFn<K, W> = FxHashFn, W: HashWidth = u32> {
+    Occupied(OccupiedEntry<'a, K, V, A, H, W>),
+    Vacant(VacantEntry<'a, K, V, A, H, W>),
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> Entry<'a, K, V, A, H, W> {
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e)   => e.key(),
+        }
+    }
+
+    #[inline]
+    pub fn slot_idx(&self) -> W::SlotIdx {
+        match self {
+            Entry::Occupied(e) => e.slot_idx(),
+            Entry::Vacant(e)   => e.slot_idx(),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e)   => e.insert(default),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e)   => e.insert(f()),
+        }
+    }
+
+    #[inline]
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, f: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e)   => {
+                let v = f(e.key());
+                e.insert(v)
+            }
+        }
+    }
+
+    #[inline]
+    pub fn or_default(self) -> &'a mut V  where V: Default {
+        self.or_insert_with(V::default)
+    }
+
+    #[inline]
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut e) => {
+                f(e.get_mut());
+                Entry::Occupied(e)
+            }
+            Entry::Vacant(e) => Entry::Vacant(e),
+        }
+    }
+}
+
+
+pub struct OccupiedEntry<'a, K, V, A: Alloc = GlobalAlloc, H: HashFn<K, W> = FxHashFn, W: HashWidth = u32> {
+    map: &'a mut HashMap<K, V, A, H, W>,
+    idx: W::SlotIdx,
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> OccupiedEntry<'a, K, V, A, H, W> {
+    #[inline(always)]
+    pub fn slot_idx(&self) -> W::SlotIdx { self.idx }
+
+    #[inline]
+    pub fn key(&self) -> &K {
+        unsafe { self.map.slot_unck(self.idx).0 }
+    }
+
+    #[inline]
+    pub fn get(&self) -> &V {
+        unsafe { self.map.slot_unck(self.idx).1 }
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { self.map.slot_unck_mut(self.idx).1 }
+    }
+
+    #[inline]
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { self.map.slot_unck_mut(self.idx).1 }
+    }
+
+    /// replaces the value and returns the old one.
+    #[inline]
+    pub fn insert(&mut self, value: V) -> V {
+        crate::mem::replace(self.get_mut(), value)
+    }
+
+    #[inline]
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    #[inline]
+    pub fn remove_entry(self) -> (K, V) {
+        unsafe { self.map.remove_at_unck(self.idx) }
+    }
+}
+
+
+pub struct VacantEntry<'a, K, V, A: Alloc = GlobalAlloc, H: HashFn<K, W> = FxHashFn, W: HashWidth = u32> {
+    map: &'a mut HashMap<K, V, A, H, W>,
+    idx: W::SlotIdx,
+    hash: W::Hash,
+    key: K,
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, W>, W: HashWidth> VacantEntry<'a, K, V, A, H, W> {
+    /// the slot, the entry will be inserted into.
+    #[inline(always)]
+    pub fn slot_idx(&self) -> W::SlotIdx { self.idx }
+
+    #[inline(always)]
+    pub fn key(&self) -> &K { &self.key }
+
+    #[inline(always)]
+    pub fn into_key(self) -> K { self.key }
+
+    #[inline]
+    pub fn insert(self, value: V) -> &'a mut V {
+        let map = self.map;
+        unsafe {
+            let none = map.insert_at_unck(self.idx, self.hash, self.key, value);
+            crate::assume!(none.is_none());
+            return map.slot_unck_mut(self.idx).1;
+        }
+    }
+}
+
+
 pub struct Iter<'a, K, V> {
     groups: *const Group,
     slots: *const (K, V),
-    rem: u32,
-    slots_num: u32,
-    slot_idx: SlotIdx,
+    rem: usize,
+    slots_num: usize,
+    slot_idx: usize,
     phantom: PhantomData<&'a (K, V)>,
 }
 
@@ -604,24 +1240,24 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> { unsafe {
-        while self.slot_idx.0 < self.slots_num {
-            let group = &*self.groups.add(self.slot_idx.0 as usize / Group::WIDTH);
+        while self.slot_idx < self.slots_num {
+            let group = &*self.groups.add(self.slot_idx / Group::WIDTH);
 
             if group.is_used(self.slot_idx) {
                 self.rem -= 1;
 
-                let idx = self.slot_idx.0 as usize;
-                self.slot_idx.0 += 1;
+                let idx = self.slot_idx;
+                self.slot_idx += 1;
 
                 let (k, v) = &*self.slots.add(idx);
                 return Some((k, v));
             }
 
             if !group.match_used().any() {
-                self.slot_idx.0 = (self.slot_idx.0 & !(Group::WIDTH as u32 - 1)) + Group::WIDTH as u32;
+                self.slot_idx = (self.slot_idx & !(Group::WIDTH - 1)) + Group::WIDTH;
             }
             else {
-                self.slot_idx.0 += 1;
+                self.slot_idx += 1;
             }
         }
         None
@@ -629,7 +1265,7 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.rem as usize, Some(self.rem as usize))
+        (self.rem, Some(self.rem))
     }
 }
 
@@ -637,9 +1273,9 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
 pub struct IterMut<'a, K, V> {
     groups: *const Group,
     slots: *mut (K, V),
-    rem: u32,
-    slots_num: u32,
-    slot_idx: SlotIdx,
+    rem: usize,
+    slots_num: usize,
+    slot_idx: usize,
     phantom: PhantomData<(&'a K, &'a mut V)>,
 }
 
@@ -648,24 +1284,24 @@ impl<'a, K, V> Iterator for IterMut<'a, K, V> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> { unsafe {
-        while self.slot_idx.0 < self.slots_num {
-            let group = &*self.groups.add(self.slot_idx.0 as usize / Group::WIDTH);
+        while self.slot_idx < self.slots_num {
+            let group = &*self.groups.add(self.slot_idx / Group::WIDTH);
 
             if group.is_used(self.slot_idx) {
                 self.rem -= 1;
 
-                let idx = self.slot_idx.0 as usize;
-                self.slot_idx.0 += 1;
+                let idx = self.slot_idx;
+                self.slot_idx += 1;
 
                 let (k, v) = &mut *self.slots.add(idx);
                 return Some((k, v));
             }
 
             if !group.match_used().any() {
-                self.slot_idx.0 = (self.slot_idx.0 & !(Group::WIDTH as u32 - 1)) + Group::WIDTH as u32;
+                self.slot_idx = (self.slot_idx & !(Group::WIDTH - 1)) + Group::WIDTH;
             }
             else {
-                self.slot_idx.0 += 1;
+                self.slot_idx += 1;
             }
         }
         None
@@ -673,127 +1309,179 @@ impl<'a, K, V> Iterator for IterMut<'a, K, V> {
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.rem as usize, Some(self.rem as usize))
+        (self.rem, Some(self.rem))
+    }
+}
+
+
+/// the first used slot at or after `from`.
+#[inline]
+fn next_used_slot(groups: *const Group, groups_num: usize, from: usize) -> Option<usize> {
+    let size = groups_num * Group::WIDTH;
+    let mut idx = from;
+    while idx < size {
+        let group_start = idx - idx % Group::WIDTH;
+        let group = unsafe { &*groups.add(idx / Group::WIDTH) };
+        for i in group.match_used() {
+            if group_start + i >= idx {
+                return Some(group_start + i);
+            }
+        }
+        idx = group_start + Group::WIDTH;
     }
+    return None;
 }
 
 
 // max load: 14/16 = 7/8.
 
-const EMPTY_PER_GROUP: u32 = Group::WIDTH as u32 * 7/8;
+pub(super) const EMPTY_PER_GROUP: usize = Group::WIDTH * 7/8;
 crate::static_assert!(Group::WIDTH % 8 == 0);
 
+/// the number of groups for `cap` entries.
+/// `None`, if that would exceed `max_slots`.
 #[inline]
-const fn num_groups_for_cap(cap: usize) -> Option<u32> {
-    const W32: u32 = Group::WIDTH as u32;
+pub(super) const fn num_groups_for_cap(cap: usize, max_slots: usize) -> Option<usize> {
     const W: usize = Group::WIDTH;
 
     // max cap we can support:
-    //  cap*8/7 + w-1 <= u32::MAX
-    //  cap <= (u32::MAX - w-1)*7/8
-    const MAX_CAP: usize = (((u32::MAX - (W32-1)) as u64)*7/8) as usize;
+    //  cap*8/7 + w-1 <= max_slots
+    //  cap <= (max_slots - w-1)*7/8
+    let max_cap = ((max_slots - (W-1)) as u128 * 7/8) as usize;
 
-    const _MAX_GROUPS: usize = (MAX_CAP + MAX_CAP/7 + (W-1)) / W;
-    crate::static_assert!(MAX_CAP <= _MAX_GROUPS*W);
-
-    if cap <= MAX_CAP {
+    if cap <= max_cap {
         let groups = (cap + cap/7 + (W-1)) / W;
-        Some(groups as u32)
+        Some(groups)
     }
     else { None }
 }
 
 
-struct Group(u64);
+crate::cfg_if! {
+    if #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))] {
+        use crate::byte_mask::ByteMask16 as GroupMask;
 
-impl Group {
-    const WIDTH: usize = crate::mem::size_of::<Group>();
+        /// 16 control bytes, scanned with sse2.
+        #[repr(C, align(16))]
+        pub(crate) struct Group([u8; 16]);
 
+        impl Group {
+            #[inline]
+            pub(super) const fn fresh() -> Group {
+                Self([Self::FRESH; 16])
+            }
 
-    #[inline]
-    fn first_idx(hash: u32, groups_num: u32) -> usize {
-        ((hash as u64 * groups_num as u64) >> 32) as usize
-    }
-
+            #[inline]
+            pub(super) fn match_tag(&self, tag: u8) -> GroupMask {
+                GroupMask::find_equal_bytes(self.0, tag)
+            }
 
-    const FRESH:     u8  = 0xff;
-    const TOMBSTONE: u8  = 0x80;
-    const HASH_MASK: u32 = 0x7f;
+            #[inline]
+            pub(super) fn match_tomb(&self) -> GroupMask {
+                GroupMask::find_equal_bytes(self.0, Self::TOMBSTONE)
+            }
 
+            #[inline]
+            pub(super) fn match_fresh(&self) -> GroupMask {
+                GroupMask::find_equal_bytes(self.0, Self::FRESH)
+            }
 
-    #[inline]
-    const fn fresh() -> Group {
-        Self(crate::byte_mask::splat_8(Self::FRESH))
+            #[inline]
+            pub(super) fn match_used(&self) -> GroupMask {
+                // used entries don't have the high bit.
+                GroupMask::find_high_bit_bytes(self.0).not()
+            }
+        }
     }
+    else {
+        use crate::byte_mask::ByteMask8 as GroupMask;
 
-    #[inline]
-    const fn mask_hash(hash: Hash32) -> u8 {
-        (hash.0 & Self::HASH_MASK) as u8
-    }
+        /// 8 control bytes, scanned with swar.
+        pub(crate) struct Group(u64);
 
+        impl Group {
+            #[inline]
+            pub(super) const fn fresh() -> Group {
+                Self(crate::byte_mask::splat_8(Self::FRESH))
+            }
 
-    #[inline]
-    fn match_hash(&self, hash: Hash32) -> ByteMask8 {
-        ByteMask8::find_equal_bytes(self.0, Self::mask_hash(hash))
-    }
+            #[inline]
+            pub(super) fn match_tag(&self, tag: u8) -> GroupMask {
+                GroupMask::find_equal_bytes(self.0, tag)
+            }
 
-    #[inline]
-    fn match_tomb(&self) -> ByteMask8 {
-        // high bit -> not used.
-        // second highest bit -> fresh.
-        ByteMask8::find_high_bit_bytes(self.0 & !(self.0 << 1))
-    }
+            #[inline]
+            pub(super) fn match_tomb(&self) -> GroupMask {
+                // high bit -> not used.
+                // second highest bit -> fresh.
+                GroupMask::find_high_bit_bytes(self.0 & !(self.0 << 1))
+            }
 
-    #[inline]
-    fn match_fresh(&self) -> ByteMask8 {
-        // high bit -> not used.
-        // second highest bit -> fresh.
-        ByteMask8::find_high_bit_bytes(self.0 & (self.0 << 1))
-    }
+            #[inline]
+            pub(super) fn match_fresh(&self) -> GroupMask {
+                // high bit -> not used.
+                // second highest bit -> fresh.
+                GroupMask::find_high_bit_bytes(self.0 & (self.0 << 1))
+            }
 
-    #[inline]
-    fn match_used(&self) -> ByteMask8 {
-        // used entries don't have the high bit.
-        ByteMask8::find_high_bit_bytes(self.0).not()
+            #[inline]
+            pub(super) fn match_used(&self) -> GroupMask {
+                // used entries don't have the high bit.
+                GroupMask::find_high_bit_bytes(self.0).not()
+            }
+        }
     }
+}
+
+/// the methods take slot indices,
+/// of which only the position within the group is used.
+impl Group {
+    pub(crate) const WIDTH: usize = crate::mem::size_of::<Group>();
+
+
+    pub(super) const FRESH:     u8 = 0xff;
+    pub(super) const TOMBSTONE: u8 = 0x80;
+    pub(super) const TAG_MASK:  u8 = 0x7f;
 
 
     #[inline]
-    fn get(&self, idx: SlotIdx) -> u8 { unsafe {
-        let bytes = (&self.0 as *const u64).cast::<u8>();
-        return bytes.add(idx.0 as usize % Group::WIDTH).read();
+    pub(super) fn get(&self, idx: usize) -> u8 { unsafe {
+        let bytes = core::ptr::addr_of!(self.0).cast::<u8>();
+        return bytes.add(idx % Group::WIDTH).read();
     }}
 
     #[inline]
-    fn set(&mut self, idx: SlotIdx, value: u8) { unsafe {
-        let bytes = (&mut self.0 as *mut u64).cast::<u8>();
-        bytes.add(idx.0 as usize % Group::WIDTH).write(value);
+    pub(super) fn set(&mut self, idx: usize, value: u8) { unsafe {
+        let bytes = core::ptr::addr_of_mut!(self.0).cast::<u8>();
+        bytes.add(idx % Group::WIDTH).write(value);
     }}
 
     #[inline]
-    fn is_used(&self, idx: SlotIdx) -> bool {
+    pub(super) fn is_used(&self, idx: usize) -> bool {
         self.get(idx) & 0x80 == 0
     }
 
     #[inline]
-    fn is_fresh(&self, idx: SlotIdx) -> bool {
+    pub(super) fn is_fresh(&self, idx: usize) -> bool {
         self.get(idx) == 0xff
     }
 
     #[inline]
-    fn use_entry(&mut self, idx: SlotIdx, hash: Hash32) {
-        self.set(idx, Self::mask_hash(hash))
+    pub(super) fn use_entry(&mut self, idx: usize, tag: u8) {
+        debug_assert!(tag & !Self::TAG_MASK == 0);
+        self.set(idx, tag)
     }
 
+    /// returns whether the slot became fresh.
     #[inline]
-    fn free_entry(&mut self, idx: SlotIdx) -> u32 {
+    pub(super) fn free_entry(&mut self, idx: usize) -> bool {
         if self.match_fresh().any() {
             self.set(idx, Self::FRESH);
-            return 1;
+            return true;
         }
         else {
             self.set(idx, Self::TOMBSTONE);
-            return 0;
+            return false;
         }
     }
 }
@@ -1066,21 +1754,23 @@ mod tests {
         assert!(iter.next().is_none());
     }
 
+    */
+
     #[test]
     fn hm_retain() {
-        let mut hm: HashMapF<String, Vec<i8>, ConstHash> = HashMap::fnew();
-        hm.insert("a".to_string(), vec![1]);
-        hm.insert("b".to_string(), vec![1, 2]);
-        hm.insert("d".to_string(), vec![1, 2, 3, 4]);
-        hm.insert("c".to_string(), vec![1, 2, 3]);
+        let mut hm: HashMap<String, std::vec::Vec<i8>, GlobalAlloc, ConstHash> = HashMap::default();
+        hm.insert("a".into(), std::vec![1]);
+        hm.insert("b".into(), std::vec![1, 2]);
+        hm.insert("d".into(), std::vec![1, 2, 3, 4]);
+        hm.insert("c".into(), std::vec![1, 2, 3]);
         assert_eq!(hm.len(), 4);
         assert_eq!(hm.resident(), 4);
 
         let mut iter = hm.iter();
-        assert_eq!(iter.next(), Some((&"a".to_string(), &vec![1])));
-        assert_eq!(iter.next(), Some((&"b".to_string(), &vec![1, 2])));
-        assert_eq!(iter.next(), Some((&"d".to_string(), &vec![1, 2, 3, 4])));
-        assert_eq!(iter.next(), Some((&"c".to_string(), &vec![1, 2, 3])));
+        assert_eq!(iter.next(), Some((&"a".into(), &std::vec![1])));
+        assert_eq!(iter.next(), Some((&"b".into(), &std::vec![1, 2])));
+        assert_eq!(iter.next(), Some((&"d".into(), &std::vec![1, 2, 3, 4])));
+        assert_eq!(iter.next(), Some((&"c".into(), &std::vec![1, 2, 3])));
         assert_eq!(iter.next(), None);
 
         hm.retain(|_, v| v.len() % 2 == 0);
@@ -1088,11 +1778,10 @@ mod tests {
         assert_eq!(hm.resident(), 2);
 
         let mut iter = hm.iter();
-        assert_eq!(iter.next(), Some((&"b".to_string(), &vec![1, 2])));
-        assert_eq!(iter.next(), Some((&"d".to_string(), &vec![1, 2, 3, 4])));
+        assert_eq!(iter.next(), Some((&"b".into(), &std::vec![1, 2])));
+        assert_eq!(iter.next(), Some((&"d".into(), &std::vec![1, 2, 3, 4])));
         assert_eq!(iter.next(), None);
     }
-    */
 
     /*
     #[test]
@@ -1424,5 +2113,357 @@ mod tests {
 
         assert_eq!(hm.get(&0), Some(&2));
     }
-}
 
+    #[test]
+    fn hm_rehash_in_place() {
+        // fill up, then remove most entries.
+        // colliding hashes, so the removes leave tombstones.
+        let mut hm: HashMap<u32, u32, _, ConstHash> = HashMap::with_hash_and_cap_in(GlobalAlloc, ConstHash, 100);
+        let size = hm.size();
+
+        let mut live = std::collections::BTreeMap::new();
+        for k in 0..hm.cap() as u32 {
+            hm.insert_new(k, k*3);
+            live.insert(k, k*3);
+        }
+        for k in 0..hm.cap() as u32 - 10 {
+            assert_eq!(hm.remove(&k), Some((k, k*3)));
+            live.remove(&k);
+        }
+        assert_eq!(hm.resident(), hm.cap());
+        assert_eq!(hm.len(), 10);
+
+        // no room, but mostly tombstones -> rehash instead of growing.
+        hm.insert_new(1000, 3000);
+        live.insert(1000, 3000);
+        assert_eq!(hm.size(), size);
+        assert_eq!(hm.resident(), hm.len());
+        assert_eq!(hm.len(), 11);
+
+        for (k, v) in &live {
+            assert_eq!(hm.get(k), Some(v));
+        }
+
+        // explicit.
+        for k in live.keys().copied().step_by(2).collect::<std::vec::Vec<_>>() {
+            hm.remove(&k);
+            live.remove(&k);
+        }
+        hm.rehash_in_place();
+        assert_eq!(hm.resident(), hm.len());
+        assert_eq!(hm.len(), live.len());
+        assert_eq!(hm.size(), size);
+        for (k, v) in &live {
+            assert_eq!(hm.get(k), Some(v));
+        }
+        assert_eq!(hm.iter().count(), live.len());
+
+        // colliding hashes, with drops.
+        let mut hm = HashMap::with_hash_fn_in(GlobalAlloc, ConstHash);
+        for i in 0..3*Group::WIDTH as u32 {
+            hm.insert(i, String::from_str("x"));
+        }
+        for i in (0..3*Group::WIDTH as u32).step_by(3) {
+            hm.remove(&i);
+        }
+        assert!(hm.resident() > hm.len());
+        hm.rehash_in_place();
+        assert_eq!(hm.resident(), hm.len());
+        for i in 0..3*Group::WIDTH as u32 {
+            assert_eq!(hm.get(&i).is_some(), i % 3 != 0);
+        }
+
+        // spread hashes.
+        let mut hm: HashMap<u32, u32> = HashMap::new();
+        for i in 0..1000 {
+            hm.insert(i, i);
+        }
+        for i in (0..1000).filter(|i| i % 3 != 0) {
+            hm.remove(&i);
+        }
+        hm.rehash_in_place();
+        assert_eq!(hm.resident(), hm.len());
+        for i in 0..1000 {
+            assert_eq!(hm.get(&i), if i % 3 == 0 { Some(&i) } else { None });
+        }
+
+        let mut empty: HashMap<u32, u32> = HashMap::new();
+        empty.rehash_in_place();
+        assert_eq!(empty.size(), 0);
+    }
+
+    #[test]
+    fn hm_kentry() {
+        let mut hm: HashMap<String, u32> = HashMap::new();
+
+        let text = "a b a c b a";
+        for word in text.split(' ') {
+            *hm.kentry(word.into()).or_insert(0) += 1;
+        }
+        assert_eq!(hm.len(), 3);
+        assert_eq!(hm["a"], 3);
+        assert_eq!(hm["b"], 2);
+        assert_eq!(hm["c"], 1);
+
+        match hm.kentry("a".into()) {
+            Entry::Occupied(mut e) => {
+                assert_eq!(*e.key(), "a");
+                assert_eq!(*e.get(), 3);
+                assert_eq!(e.insert(7), 3);
+                assert_eq!(e.slot_idx(), hm_idx(&hm, "a"));
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(hm["a"], 7);
+
+        match hm.kentry("d".into()) {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(e) => {
+                assert_eq!(*e.key(), "d");
+                let idx = e.slot_idx();
+                *e.insert(4) += 1;
+                assert_eq!(hm.slot(idx), (&"d".into(), &5));
+            }
+        }
+        assert_eq!(hm.len(), 4);
+
+        let v = hm.kentry("e".into()).and_modify(|_| unreachable!()).or_insert_with(|| 9);
+        assert_eq!(*v, 9);
+        let v = hm.kentry("e".into()).and_modify(|v| *v *= 2).or_insert_with(|| unreachable!());
+        assert_eq!(*v, 18);
+        let v = hm.kentry("ff".into()).or_insert_with_key(|k| k.len() as u32);
+        assert_eq!(*v, 2);
+        assert_eq!(*hm.kentry("g".into()).or_default(), 0);
+        assert_eq!(*hm.kentry("g".into()).key(), "g");
+        assert_eq!(hm.len(), 7);
+
+        let Entry::Occupied(e) = hm.kentry("b".into()) else { unreachable!() };
+        assert_eq!(e.remove_entry(), ("b".into(), 2));
+        let Entry::Occupied(e) = hm.kentry("c".into()) else { unreachable!() };
+        assert_eq!(e.remove(), 1);
+        let Entry::Vacant(e) = hm.kentry("c".into()) else { unreachable!() };
+        assert_eq!(e.into_key(), "c");
+        assert_eq!(hm.len(), 5);
+        assert!(hm.get("b").is_none());
+        assert!(hm.get("c").is_none());
+    }
+
+    fn hm_idx(hm: &HashMap<String, u32>, k: &str) -> SlotIdx {
+        let (present, idx) = hm.entry(k);
+        assert!(present);
+        return idx;
+    }
+
+    #[test]
+    fn hm_drain_and_into_iter() {
+        use crate::vec::Vec;
+        use crate::ext::FromIn;
+
+        let mut hm: HashMap<u32, String> = (0..100).map(|i| (i, crate::format!("{i}"))).collect();
+        assert_eq!(hm.len(), 100);
+        let size = hm.size();
+
+        // extract_if.
+        let mut odd: Vec<(u32, String)> = hm.extract_if(|k, v| {
+            v.push("!");
+            k % 2 == 1
+        }).collect();
+        odd.sort_by_key(|(k, _)| *k);
+        assert_eq!(odd.len(), 50);
+        for (i, (k, v)) in odd.iter().enumerate() {
+            assert_eq!(*k, 2*i as u32 + 1);
+            assert_eq!(*v, crate::format!("{k}!"));
+        }
+        assert_eq!(hm.len(), 50);
+        assert_eq!(hm.get(&1), None);
+        assert_eq!(*hm.get(&2).unwrap(), "2!");
+
+        // partial extract_if keeps the rest.
+        let first = hm.extract_if(|_, _| true).next().unwrap();
+        assert_eq!(hm.len(), 49);
+        assert!(hm.get(&first.0).is_none());
+
+        // partial drain drops the rest.
+        let mut drain = hm.drain();
+        assert_eq!(drain.size_hint(), (49, Some(49)));
+        assert!(drain.next().is_some());
+        drop(drain);
+        assert_eq!(hm.len(), 0);
+        assert_eq!(hm.resident(), 0);
+        assert_eq!(hm.size(), size);
+        assert!(hm.iter().next().is_none());
+
+        // extend & reuse.
+        hm.extend((0..10).map(|i| (i, crate::format!("{i}"))));
+        hm.extend([(3, "x".into())]);
+        assert_eq!(hm.len(), 10);
+        assert_eq!(*hm.get(&3).unwrap(), "x");
+
+        let mut drained: Vec<u32> = hm.drain().map(|(k, _)| k).collect();
+        drained.sort();
+        assert_eq!(drained.as_slice(), [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        // owned iteration.
+        let hm: HashMap<u32, String> = HashMap::from_in(GlobalAlloc, (0..20).map(|i| (i, crate::format!("{i}"))));
+        let mut iter = hm.into_iter();
+        assert_eq!(iter.size_hint(), (20, Some(20)));
+        let mut seen = [false; 20];
+        for _ in 0..10 {
+            let (k, v) = iter.next().unwrap();
+            assert_eq!(v, crate::format!("{k}"));
+            seen[k as usize] = true;
+        }
+        assert_eq!(iter.size_hint(), (10, Some(10)));
+        // drops the rest and frees.
+        drop(iter);
+        assert_eq!(seen.iter().filter(|s| **s).count(), 10);
+
+        let mut sum = 0;
+        let hm: HashMap<u32, u32> = (1..=4).map(|i| (i, i*i)).collect();
+        for (k, v) in &hm {
+            sum += k + v;
+        }
+        assert_eq!(sum, 10 + 30);
+        assert_eq!(hm.into_iter().map(|(_, v)| v).sum::<u32>(), 30);
+
+        let empty: HashMap<u32, u32> = HashMap::new();
+        assert!(empty.into_iter().next().is_none());
+    }
+
+    #[test]
+    fn hm_send_sync() {
+        fn is_send<T: Send>() {}
+        fn is_sync<T: Sync>() {}
+        is_send::<HashMap<u32, String>>();
+        is_sync::<HashMap<u32, String>>();
+        is_send::<IntoIter<u32, String, GlobalAlloc>>();
+        is_sync::<IntoIter<u32, String, GlobalAlloc>>();
+        is_send::<crate::hash::hash_set::IntoIter<u32, GlobalAlloc>>();
+
+        // consume on another thread.
+        let hm: HashMap<u32, u32> = (0..100).map(|i| (i, i)).collect();
+        let iter = hm.into_iter();
+        let sum = std::thread::spawn(move || iter.map(|(_, v)| v).sum::<u32>()).join().unwrap();
+        assert_eq!(sum, 4950);
+    }
+
+    #[test]
+    fn hm_hash_distribution() {
+        use crate::hash::wyhash::WyHashFn;
+        use std::format;
+
+        // chi-squared over `n` buckets, should be close to `n - 1`.
+        fn chi2(counts: &[u32]) -> f64 {
+            let total: u32 = counts.iter().sum();
+            let expected = total as f64 / counts.len() as f64;
+            counts.iter().map(|c| {
+                let d = *c as f64 - expected;
+                d*d / expected
+            }).sum()
+        }
+
+        fn check<T: Hash>(keys: impl Iterator<Item=T>) {
+            // group index (high bits) for a power of two and a prime.
+            let mut groups_64 = [0u32; 64];
+            let mut groups_97 = [0u32; 97];
+            // control byte (low bits).
+            let mut tags = [0u32; 128];
+            // same for the 64 bit width.
+            let mut groups_64_w = [0u32; 64];
+            let mut groups_97_w = [0u32; 97];
+            let mut tags_w = [0u32; 128];
+            for k in keys {
+                let h = Hash32(WyHashFn.hash(&k));
+                groups_64[u32::first_group(h, 64)] += 1;
+                groups_97[u32::first_group(h, 97)] += 1;
+                tags[u32::tag(h) as usize] += 1;
+
+                let h = Hash64(WyHashFn.hash(&k));
+                groups_64_w[u64::first_group(h, 64)] += 1;
+                groups_97_w[u64::first_group(h, 97)] += 1;
+                tags_w[u64::tag(h) as usize] += 1;
+            }
+            // ~p=0.001 for the respective degrees of freedom.
+            for (groups_64, groups_97, tags) in [(groups_64, groups_97, tags), (groups_64_w, groups_97_w, tags_w)] {
+                assert!(chi2(&groups_64) < 104.0, "{}", chi2(&groups_64));
+                assert!(chi2(&groups_97) < 147.0, "{}", chi2(&groups_97));
+                assert!(chi2(&tags)      < 182.0, "{}", chi2(&tags));
+            }
+        }
+
+        const N: u32 = 64*97*4;
+        check(0..N);
+        check((0..N).map(|i| i << 16));
+        check((0..N as u64).map(|i| i << 40));
+        check((0..N).map(|i| format!("ident_{i}")));
+        check((0..N).map(|i| format!("src/module_{}/file_{}.rs", i / 100, i % 100)));
+
+        // usable as the `H` param.
+        let mut hm: HashMap<String, u32, GlobalAlloc, WyHashFn> = HashMap::with_hash_fn_in(GlobalAlloc, WyHashFn);
+        for i in 0..1000 {
+            hm.insert(format!("ident_{i}").as_str().into(), i);
+        }
+        for i in 0..1000 {
+            assert_eq!(hm[format!("ident_{i}").as_str()], i);
+        }
+    }
+
+    #[test]
+    fn hm_64() {
+        // max capacity depends on the width.
+        #[cfg(target_pointer_width = "64")] {
+            let cap = u32::MAX as usize;
+            assert!(num_groups_for_cap(cap, u32::MAX_USIZE).is_none());
+            assert!(num_groups_for_cap(cap, u64::MAX_USIZE).is_some());
+        }
+
+        let mut hm = HashMap64::<u32, u32>::new64();
+        assert_eq!(hm.size(), 0);
+        assert_eq!(hm.entry(&1), (false, SlotIdx64(0)));
+
+        for i in 0..100 {
+            assert_eq!(hm.insert(i, 2*i), None);
+        }
+        assert_eq!(hm.len(), 100);
+        assert_eq!(hm.insert(5, 1), Some((5, 10)));
+        assert_eq!(hm[&5], 1);
+        assert_eq!(hm.remove(&5), Some((5, 1)));
+
+        // 64 bit hashes.
+        let h: u64 = FxHashFn.hash(&7u32);
+        assert_eq!(hm.hash(&7), Hash64(h));
+
+        let (present, idx) = hm.entry(&7);
+        assert!(present);
+        assert_eq!(hm.slot(idx), (&7, &14));
+
+        *hm.kentry(200).or_default() += 3;
+        *hm.kentry(200).or_default() += 3;
+        assert_eq!(hm[&200], 6);
+
+        hm.retain(|k, _| k % 2 == 0);
+        assert_eq!(hm.len(), 50 + 1);
+        let mut sum = 0;
+        for (k, v) in &hm {
+            assert!(*v == 2*k || *k == 200);
+            sum += v;
+        }
+        assert_eq!(sum, (0..100).filter(|i| i % 2 == 0).map(|i| 2*i).sum::<u32>() + 6);
+
+        let hm2 = hm.clone();
+        assert_eq!(hm2.into_iter().count(), 51);
+
+        let mut with_cap: HashMap64<u32, u32> = HashMap64::with_cap64(10);
+        assert!(with_cap.cap() >= 10);
+        with_cap.insert(1, 2);
+        assert_eq!(with_cap[&1], 2);
+        let in_alloc: HashMap64<u32, u32> = HashMap64::with_cap64_in(GlobalAlloc, 10);
+        assert!(in_alloc.cap() >= 10);
+        assert_eq!(HashMap64::<u32, u32>::new64_in(GlobalAlloc).len(), 0);
+        assert_eq!(HashMap64::<u32, u32>::default().len(), 0);
+
+        let collected: HashMap64<u32, u32> = (0..10).map(|i| (i, i)).collect();
+        assert_eq!(collected.len(), 10);
+        assert_eq!(collected[&9], 9);
+    }
+}
@@ -0,0 +1,534 @@
+use crate::borrow::Borrow;
+use crate::ext::FromIn;
+use crate::alloc::{Alloc, GlobalAlloc};
+use crate::hash::{Hash, HashFn, fxhash::FxHashFn};
+use crate::hash::hash_map::{self, HashMap, SlotIdx, Hash32};
+
+
+/// a hash set.
+///
+/// - a `HashMap<K, ()>`.
+/// - `insert` keeps the existing key, if the set already contains an equal one.
+pub struct HashSet<K, A: Alloc = GlobalAlloc, H: HashFn<K, u32> = FxHashFn> {
+    map: HashMap<K, (), A, H>,
+}
+
+impl<K: Hash> HashSet<K, GlobalAlloc, FxHashFn> {
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self::new_in(GlobalAlloc)
+    }
+
+    #[inline(always)]
+    pub fn with_cap(cap: usize) -> Self {
+        Self::with_cap_in(GlobalAlloc, cap)
+    }
+}
+
+impl<K: Hash, A: Alloc> HashSet<K, A, FxHashFn> {
+    #[inline(always)]
+    pub const fn new_in(alloc: A) -> Self {
+        Self { map: HashMap::new_in(alloc) }
+    }
+
+    #[inline(always)]
+    pub fn with_cap_in(alloc: A, cap: usize) -> Self {
+        Self { map: HashMap::with_cap_in(alloc, cap) }
+    }
+}
+
+impl<K, A: Alloc, H: HashFn<K, u32>> HashSet<K, A, H> {
+    #[inline(always)]
+    pub const fn with_hash_fn_in(alloc: A, h: H) -> Self {
+        Self { map: HashMap::with_hash_fn_in(alloc, h) }
+    }
+
+    #[inline(always)]
+    pub fn with_hash_and_cap_in(alloc: A, h: H, cap: usize) -> Self {
+        Self { map: HashMap::with_hash_and_cap_in(alloc, h, cap) }
+    }
+
+    #[inline(always)]
+    pub fn alloc(&self) -> &A { self.map.alloc() }
+
+    #[inline(always)]
+    pub fn hash_fn(&self) -> &H { self.map.hash_fn() }
+
+    #[inline(always)]
+    pub fn size(&self) -> usize { self.map.size() }
+
+    #[inline(always)]
+    pub fn cap(&self) -> usize { self.map.cap() }
+
+    #[inline(always)]
+    pub fn resident(&self) -> usize { self.map.resident() }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize { self.map.len() }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool { self.map.len() == 0 }
+
+
+    #[inline(always)]
+    pub fn reserve(&mut self, min_cap: usize) {
+        self.map.reserve(min_cap)
+    }
+
+    #[inline(always)]
+    pub fn reserve_for_insert(&mut self) {
+        self.map.reserve_for_insert()
+    }
+
+    #[inline(always)]
+    pub fn rehash_in_place(&mut self) {
+        self.map.rehash_in_place()
+    }
+
+
+    #[inline(always)]
+    pub fn hash<Q>(&self, k: &Q) -> Hash32
+    where Q: ?Sized, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.map.hash(k)
+    }
+
+    #[inline(always)]
+    pub fn lookup<Q>(&self, q: &Q, hash: Hash32) -> (bool, SlotIdx)
+    where Q: ?Sized + Eq, K: Borrow<Q> {
+        self.map.lookup(q, hash)
+    }
+
+    #[inline(always)]
+    pub fn lookup_cmp(&self, hash: Hash32, cmp: impl Fn(&K) -> bool) -> (bool, SlotIdx) {
+        self.map.lookup_cmp(hash, cmp)
+    }
+
+    #[inline(always)]
+    pub fn lookup_for_insert<Q>(&mut self, k: &Q, hash: Hash32) -> (bool, SlotIdx)
+    where Q: ?Sized + Eq, K: Borrow<Q> {
+        self.map.lookup_for_insert(k, hash)
+    }
+
+    #[inline(always)]
+    pub fn entry<Q>(&self, k: &Q) -> (bool, SlotIdx)
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.map.entry(k)
+    }
+
+    #[inline(always)]
+    pub fn entry_for_insert<Q>(&mut self, k: &Q) -> (bool, SlotIdx)
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.map.entry_for_insert(k)
+    }
+
+
+    #[inline(always)]
+    pub fn slot_present(&self, idx: SlotIdx) -> bool {
+        self.map.slot_present(idx)
+    }
+
+    #[inline(always)]
+    pub fn slot(&self, idx: SlotIdx) -> &K {
+        self.map.slot(idx).0
+    }
+
+    #[inline(always)]
+    pub unsafe fn slot_unck(&self, idx: SlotIdx) -> &K {
+        unsafe { self.map.slot_unck(idx).0 }
+    }
+
+    #[inline(always)]
+    pub fn insert_at(&mut self, idx: SlotIdx, hash: Hash32, k: K) -> Option<K> {
+        self.map.insert_at(idx, hash, k, ()).map(|(k, _)| k)
+    }
+
+    #[inline(always)]
+    pub unsafe fn insert_at_unck(&mut self, idx: SlotIdx, hash: Hash32, k: K) -> Option<K> {
+        unsafe { self.map.insert_at_unck(idx, hash, k, ()).map(|(k, _)| k) }
+    }
+
+    #[inline(always)]
+    pub fn remove_at(&mut self, idx: SlotIdx) -> K {
+        self.map.remove_at(idx).0
+    }
+
+    #[inline(always)]
+    pub unsafe fn remove_at_unck(&mut self, idx: SlotIdx) -> K {
+        unsafe { self.map.remove_at_unck(idx).0 }
+    }
+
+
+    #[inline]
+    pub fn contains<Q>(&self, k: &Q) -> bool
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.map.entry(k).0
+    }
+
+    #[inline]
+    pub fn get<Q>(&self, k: &Q) -> Option<&K>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let (present, idx) = self.map.entry(k);
+        if present {
+            Some(unsafe { self.map.slot_unck(idx).0 })
+        }
+        else { None }
+    }
+
+    /// - returns `true`, if `k` was inserted.
+    /// - returns `false`, if the set already contained an equal key.
+    ///   the existing key is kept.
+    #[inline]
+    pub fn insert(&mut self, k: K) -> bool
+    where K: Eq {
+        let hash = self.map.hash(&k);
+        let (present, idx) = self.map.lookup_for_insert(&k, hash);
+        if present {
+            return false;
+        }
+
+        unsafe {
+            let none = self.map.insert_at_unck(idx, hash, k, ());
+            crate::assume!(none.is_none());
+        }
+        return true;
+    }
+
+    /// inserts `k`, replacing an equal key.
+    ///
+    /// - returns the replaced key.
+    #[inline]
+    pub fn replace(&mut self, k: K) -> Option<K>
+    where K: Eq {
+        self.map.insert(k, ()).map(|(k, _)| k)
+    }
+
+    /// - returns whether the set contained `k`.
+    #[inline]
+    pub fn remove<Q>(&mut self, k: &Q) -> bool
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.map.remove(k).is_some()
+    }
+
+    #[inline]
+    pub fn take<Q>(&mut self, k: &Q) -> Option<K>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.map.remove(k).map(|(k, _)| k)
+    }
+
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    #[inline]
+    pub fn retain<F: FnMut(&K) -> bool>(&mut self, mut f: F) {
+        self.map.retain(|k, _| f(k))
+    }
+
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, K, A, H> {
+        Drain { inner: self.map.drain() }
+    }
+
+    #[inline]
+    pub fn clone_in<A2: Alloc>(&self, alloc: A2) -> HashSet<K, A2, H>
+    where K: Clone, H: Clone {
+        HashSet { map: self.map.clone_in(alloc) }
+    }
+
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter { inner: self.map.iter() }
+    }
+
+
+    /// the keys in `self` or `other`.
+    ///
+    /// - the keys in `self` first, then the ones only in `other`.
+    #[inline]
+    pub fn union<'a, A2: Alloc, H2: HashFn<K, u32>>(&'a self, other: &'a HashSet<K, A2, H2>) -> impl Iterator<Item = &'a K>
+    where K: Eq {
+        self.iter().chain(other.difference(self))
+    }
+
+    /// the keys in both `self` and `other`.
+    #[inline]
+    pub fn intersection<'a, A2: Alloc, H2: HashFn<K, u32>>(&'a self, other: &'a HashSet<K, A2, H2>) -> impl Iterator<Item = &'a K>
+    where K: Eq {
+        self.iter().filter(move |k| other.contains(*k))
+    }
+
+    /// the keys in `self`, but not in `other`.
+    #[inline]
+    pub fn difference<'a, A2: Alloc, H2: HashFn<K, u32>>(&'a self, other: &'a HashSet<K, A2, H2>) -> impl Iterator<Item = &'a K>
+    where K: Eq {
+        self.iter().filter(move |k| !other.contains(*k))
+    }
+
+    /// the keys in either `self` or `other`, but not both.
+    #[inline]
+    pub fn symmetric_difference<'a, A2: Alloc, H2: HashFn<K, u32>>(&'a self, other: &'a HashSet<K, A2, H2>) -> impl Iterator<Item = &'a K>
+    where K: Eq {
+        self.difference(other).chain(other.difference(self))
+    }
+
+    /// whether all keys of `self` are in `other`.
+    #[inline]
+    pub fn is_subset<A2: Alloc, H2: HashFn<K, u32>>(&self, other: &HashSet<K, A2, H2>) -> bool
+    where K: Eq {
+        self.len() <= other.len() && self.iter().all(|k| other.contains(k))
+    }
+
+    /// whether all keys of `other` are in `self`.
+    #[inline]
+    pub fn is_superset<A2: Alloc, H2: HashFn<K, u32>>(&self, other: &HashSet<K, A2, H2>) -> bool
+    where K: Eq {
+        other.is_subset(self)
+    }
+
+    #[inline]
+    pub fn is_disjoint<A2: Alloc, H2: HashFn<K, u32>>(&self, other: &HashSet<K, A2, H2>) -> bool
+    where K: Eq {
+        if self.len() <= other.len() {
+            self.iter().all(|k| !other.contains(k))
+        }
+        else {
+            other.iter().all(|k| !self.contains(k))
+        }
+    }
+}
+
+
+impl<K: Clone, A: Alloc + Clone, H: HashFn<K, u32> + Clone> Clone for HashSet<K, A, H> {
+    #[inline]
+    fn clone(&self) -> Self {
+        self.clone_in(self.alloc().clone())
+    }
+}
+
+impl<K, A: Alloc + Default, H: HashFn<K, u32> + Default> Default for HashSet<K, A, H> {
+    #[inline]
+    fn default() -> Self {
+        Self::with_hash_fn_in(A::default(), H::default())
+    }
+}
+
+impl<K: crate::fmt::Debug, A: Alloc, H: HashFn<K, u32>> crate::fmt::Debug for HashSet<K, A, H> {
+    fn fmt(&self, f: &mut crate::fmt::Formatter) -> crate::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Eq, A: Alloc, H: HashFn<K, u32>, A2: Alloc, H2: HashFn<K, u32>> PartialEq<HashSet<K, A2, H2>> for HashSet<K, A, H> {
+    #[inline]
+    fn eq(&self, other: &HashSet<K, A2, H2>) -> bool {
+        self.len() == other.len() && self.is_subset(other)
+    }
+}
+
+impl<K: Eq, A: Alloc, H: HashFn<K, u32>> Eq for HashSet<K, A, H> {}
+
+
+impl<K: Hash + Eq, A: Alloc, I: Iterator<Item = K>> FromIn<I, A> for HashSet<K, A, FxHashFn> {
+    #[inline]
+    fn from_in(alloc: A, iter: I) -> Self {
+        let mut result = HashSet::new_in(alloc);
+        result.extend(iter);
+        return result;
+    }
+}
+
+impl<K: Hash + Eq> FromIterator<K> for HashSet<K, GlobalAlloc, FxHashFn> {
+    fn from_iter<T: IntoIterator<Item = K>>(iter: T) -> Self {
+        Self::from_in(GlobalAlloc, iter.into_iter())
+    }
+}
+
+impl<K: Eq, A: Alloc, H: HashFn<K, u32>> Extend<K> for HashSet<K, A, H> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+
+        let (min_len, _) = iter.size_hint();
+        self.reserve(self.len() + min_len);
+
+        for k in iter {
+            self.insert(k);
+        }
+    }
+}
+
+impl<K, A: Alloc, H: HashFn<K, u32>> IntoIterator for HashSet<K, A, H> {
+    type IntoIter = IntoIter<K, A>;
+    type Item = K;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { inner: self.map.into_iter() }
+    }
+}
+
+impl<'a, K, A: Alloc, H: HashFn<K, u32>> IntoIterator for &'a HashSet<K, A, H> {
+    type IntoIter = Iter<'a, K>;
+    type Item = &'a K;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+
+pub struct Iter<'a, K> {
+    inner: hash_map::Iter<'a, K, ()>,
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+
+pub struct IntoIter<K, A: Alloc> {
+    inner: hash_map::IntoIter<K, (), A>,
+}
+
+impl<K, A: Alloc> Iterator for IntoIter<K, A> {
+    type Item = K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+
+pub struct Drain<'a, K, A: Alloc = GlobalAlloc, H: HashFn<K, u32> = FxHashFn> {
+    inner: hash_map::Drain<'a, K, (), A, H>,
+}
+
+impl<'a, K, A: Alloc, H: HashFn<K, u32>> Iterator for Drain<'a, K, A, H> {
+    type Item = K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::string::String;
+    use crate::arena::Arena;
+
+    fn sorted<'a>(iter: impl Iterator<Item = &'a u32>) -> std::vec::Vec<u32> {
+        let mut result: std::vec::Vec<u32> = iter.copied().collect();
+        result.sort();
+        return result;
+    }
+
+    #[test]
+    fn hs_basic() {
+        let mut hs: HashSet<String> = HashSet::new();
+        assert!(hs.is_empty());
+        assert!(!hs.contains("a"));
+
+        assert_eq!(hs.insert("a".into()), true);
+        assert_eq!(hs.insert("b".into()), true);
+        assert_eq!(hs.insert("a".into()), false);
+        assert_eq!(hs.len(), 2);
+        assert!(hs.contains("a"));
+        assert_eq!(*hs.get("b").unwrap(), "b");
+
+        assert_eq!(hs.replace("b".into()).unwrap(), "b");
+        assert_eq!(hs.take("b").unwrap(), "b");
+        assert_eq!(hs.remove("b"), false);
+        assert_eq!(hs.remove("a"), true);
+        assert!(hs.is_empty());
+
+        // low level api.
+        let hash = hs.hash("c");
+        let (present, idx) = hs.lookup_for_insert("c", hash);
+        assert!(!present);
+        assert!(hs.insert_at(idx, hash, "c".into()).is_none());
+        assert_eq!(hs.entry("c"), (true, idx));
+        assert_eq!(*hs.slot(idx), "c");
+        assert_eq!(hs.remove_at(idx), "c");
+        assert!(!hs.slot_present(idx));
+    }
+
+    #[test]
+    fn hs_algebra() {
+        let a: HashSet<u32> = (0..10).collect();
+        let b: HashSet<u32> = (5..15).collect();
+        let c: HashSet<u32> = (6..8).collect();
+
+        assert_eq!(sorted(a.union(&b)), (0..15).collect::<std::vec::Vec<_>>());
+        assert_eq!(sorted(a.intersection(&b)), [5, 6, 7, 8, 9]);
+        assert_eq!(sorted(a.difference(&b)), [0, 1, 2, 3, 4]);
+        assert_eq!(sorted(b.difference(&a)), [10, 11, 12, 13, 14]);
+        assert_eq!(sorted(a.symmetric_difference(&b)), [0, 1, 2, 3, 4, 10, 11, 12, 13, 14]);
+
+        assert!(c.is_subset(&a));
+        assert!(c.is_subset(&b));
+        assert!(!a.is_subset(&b));
+        assert!(a.is_superset(&c));
+        assert!(!a.is_disjoint(&b));
+        assert!(c.is_disjoint(&(10..20).collect()));
+
+        // different allocators.
+        let arena = Arena::new();
+        let d = a.clone_in(&arena);
+        assert_eq!(d.len(), 10);
+        assert!(d == a);
+        assert!(d != b);
+        assert!(d.is_subset(&a) && a.is_subset(&d));
+        assert_eq!(sorted(d.intersection(&c)), [6, 7]);
+    }
+
+    #[test]
+    fn hs_iter() {
+        let mut hs: HashSet<u32> = HashSet::with_cap(20);
+        hs.extend(0..20);
+        hs.extend([3, 4]);
+        assert_eq!(hs.len(), 20);
+
+        hs.retain(|k| k % 2 == 0);
+        assert_eq!(sorted(hs.iter()), (0..20).step_by(2).collect::<std::vec::Vec<_>>());
+        assert_eq!(sorted((&hs).into_iter()).len(), 10);
+
+        let cloned = hs.clone();
+        let mut drained: std::vec::Vec<u32> = hs.drain().collect();
+        drained.sort();
+        assert_eq!(drained, (0..20).step_by(2).collect::<std::vec::Vec<_>>());
+        assert!(hs.is_empty());
+
+        let mut owned: std::vec::Vec<u32> = cloned.into_iter().collect();
+        owned.sort();
+        assert_eq!(owned, drained);
+    }
+}
@@ -0,0 +1,999 @@
+use crate::mem::NonNull;
+use crate::borrow::Borrow;
+use crate::ext::FromIn;
+use crate::key::Key;
+use crate::vec::KVec;
+use crate::alloc::{Alloc, GlobalAlloc, Layout, cat_join, cat_next_mut};
+use crate::hash::{Hash, HashFn, fxhash::FxHashFn};
+use crate::hash::hash_map::{Hash32, SlotIdx, HashWidth, Group, EMPTY_PER_GROUP, num_groups_for_cap};
+
+
+/// a hash map, that remembers the insertion order.
+///
+/// - the entries are stored densely in a `KVec`,
+///   iteration visits them in order.
+/// - the entries' indices are typed as `I`.
+///   they are stable, unless entries are removed or sorted.
+/// - lookups go through a table of `u32` indices,
+///   which uses the same groups and probing as `HashMap`.
+pub struct IndexMap<K, V, A: Alloc = GlobalAlloc, H: HashFn<K, u32> = FxHashFn, I: Key = u32> {
+    h: H,
+    entries: KVec<I, Bucket<K, V>, A>,
+    table: IndexTable,
+}
+
+#[derive(Clone)]
+struct Bucket<K, V> {
+    hash: Hash32,
+    key: K,
+    value: V,
+}
+
+impl<K: Hash, V, I: Key> IndexMap<K, V, GlobalAlloc, FxHashFn, I> {
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self::new_in(GlobalAlloc)
+    }
+
+    #[inline(always)]
+    pub fn with_cap(cap: usize) -> Self {
+        Self::with_cap_in(GlobalAlloc, cap)
+    }
+}
+
+impl<K: Hash, V, A: Alloc, I: Key> IndexMap<K, V, A, FxHashFn, I> {
+    #[inline(always)]
+    pub const fn new_in(alloc: A) -> Self {
+        Self::with_hash_fn_in(alloc, FxHashFn)
+    }
+
+    #[inline(always)]
+    pub fn with_cap_in(alloc: A, cap: usize) -> Self {
+        Self::with_hash_and_cap_in(alloc, FxHashFn, cap)
+    }
+}
+
+impl<K, V, A: Alloc, H: HashFn<K, u32>, I: Key> IndexMap<K, V, A, H, I> {
+    #[inline]
+    pub const fn with_hash_fn_in(alloc: A, h: H) -> Self {
+        Self {
+            h,
+            entries: KVec::new_in(alloc),
+            table: IndexTable::new(),
+        }
+    }
+
+    pub fn with_hash_and_cap_in(alloc: A, h: H, cap: usize) -> Self {
+        let mut this = Self::with_hash_fn_in(alloc, h);
+        this.reserve(cap);
+        return this;
+    }
+
+    #[inline]
+    pub fn alloc(&self) -> &A {
+        self.entries.alloc()
+    }
+
+    #[inline]
+    pub fn hash_fn(&self) -> &H {
+        &self.h
+    }
+
+    /// the number of entries, before the index table needs to grow.
+    #[inline]
+    pub fn cap(&self) -> usize {
+        self.table.cap()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn klen(&self) -> I {
+        self.entries.klen()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.len() == 0
+    }
+
+
+    pub fn reserve(&mut self, min_cap: usize) {
+        self.entries.reserve(min_cap);
+
+        let min_groups_num = num_groups_for_cap(min_cap, u32::MAX_USIZE).unwrap() as u32;
+        if min_groups_num > self.table.groups_num {
+            self.rebuild_table(min_groups_num);
+        }
+    }
+
+    /// makes sure, there's room for one more entry in the index table.
+    ///
+    /// - like `HashMap`, the table is rebuilt at the same size,
+    ///   if tombstones use up most of the capacity.
+    #[inline]
+    fn reserve_for_insert(&mut self) {
+        if self.table.empty == 0 {
+            let groups_num = self.table.groups_num;
+            if groups_num != 0 && self.len() <= self.cap() / 2 {
+                self.rebuild_table(groups_num);
+            }
+            else {
+                self.rebuild_table(groups_num.checked_mul(2).unwrap().max(1));
+            }
+        }
+    }
+
+    #[inline]
+    fn rebuild_table(&mut self, groups_num: u32) {
+        let hashes = self.entries.iter().map(|b| b.hash);
+        self.table.rebuild(self.entries.alloc(), groups_num, hashes);
+    }
+
+
+    #[inline(always)]
+    pub fn hash<Q>(&self, k: &Q) -> Hash32
+    where Q: ?Sized, K: Borrow<Q>, H: HashFn<Q, u32> {
+        Hash32(self.h.hash(k))
+    }
+
+    /// the index table slot for `q`.
+    /// if `q` isn't present, the slot, where it would be inserted.
+    #[inline]
+    fn lookup<Q>(&self, q: &Q, hash: Hash32) -> (bool, SlotIdx)
+    where Q: ?Sized + Eq, K: Borrow<Q> {
+        let entries = self.entries.as_slice();
+        return self.table.lookup(hash, |i| {
+            let b = unsafe { entries.get_unchecked(i as usize) };
+            b.hash == hash && b.key.borrow() == q
+        });
+    }
+
+    /// the index table slot for the entry at `index`.
+    #[inline]
+    fn slot_of(&self, index: usize) -> SlotIdx {
+        let hash = self.entries.as_slice()[index].hash;
+        let (present, idx) = self.table.lookup(hash, |i| i as usize == index);
+        assert!(present);
+        return idx;
+    }
+
+
+    #[inline]
+    pub fn get_index_of<Q>(&self, k: &Q) -> Option<I>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let (present, idx) = self.lookup(k, self.hash(k));
+        if present {
+            Some(unsafe { I::from_usize_unck(self.table.get(idx) as usize) })
+        }
+        else { None }
+    }
+
+    #[inline]
+    pub fn get_full<Q>(&self, k: &Q) -> Option<(I, &K, &V)>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let index = self.get_index_of(k)?;
+        let b = &self.entries[index];
+        return Some((index, &b.key, &b.value));
+    }
+
+    #[inline]
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let index = self.get_index_of(k)?;
+        return Some(&self.entries[index].value);
+    }
+
+    #[inline]
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let index = self.get_index_of(k)?;
+        return Some(&mut self.entries[index].value);
+    }
+
+    #[inline]
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.get_index_of(k).is_some()
+    }
+
+
+    #[inline]
+    pub fn get_index(&self, index: I) -> Option<(&K, &V)> {
+        let b = self.entries.kget(index)?;
+        return Some((&b.key, &b.value));
+    }
+
+    #[inline]
+    pub fn get_index_mut(&mut self, index: I) -> Option<(&K, &mut V)> {
+        let b = self.entries.kget_mut(index)?;
+        return Some((&b.key, &mut b.value));
+    }
+
+    #[inline]
+    pub fn first(&self) -> Option<(&K, &V)> {
+        let b = self.entries.first()?;
+        return Some((&b.key, &b.value));
+    }
+
+    #[inline]
+    pub fn last(&self) -> Option<(&K, &V)> {
+        let b = self.entries.last()?;
+        return Some((&b.key, &b.value));
+    }
+
+
+    /// inserts or replaces the value for `k`.
+    ///
+    /// - returns the entry's index and the old value.
+    /// - new entries are appended, existing entries keep their index (and key).
+    pub fn insert_full(&mut self, k: K, v: V) -> (I, Option<V>)
+    where K: Eq {
+        let hash = self.hash(&k);
+        self.reserve_for_insert();
+
+        let (present, idx) = self.lookup(&k, hash);
+        if present {
+            let index = unsafe { I::from_usize_unck(self.table.get(idx) as usize) };
+            let old = crate::mem::replace(&mut self.entries[index].value, v);
+            return (index, Some(old));
+        }
+
+        let index = self.entries.len();
+        assert!(index < u32::MAX as usize);
+
+        let kindex = self.entries.push(Bucket { hash, key: k, value: v });
+        unsafe { self.table.insert_at(idx, hash, index as u32) }
+        return (kindex, None);
+    }
+
+    #[inline]
+    pub fn insert(&mut self, k: K, v: V) -> Option<V>
+    where K: Eq {
+        self.insert_full(k, v).1
+    }
+
+
+    /// removes the entry for `k`, and moves the last entry into its place.
+    ///
+    /// - `O(1)`, but changes the order.
+    pub fn swap_remove_full<Q>(&mut self, k: &Q) -> Option<(I, K, V)>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let (present, idx) = self.lookup(k, self.hash(k));
+        if !present { return None }
+
+        let index = self.table.get(idx) as usize;
+        unsafe { self.table.remove_at(idx) }
+        let (k, v) = self.swap_remove_finish(index);
+        return Some((unsafe { I::from_usize_unck(index) }, k, v));
+    }
+
+    #[inline]
+    pub fn swap_remove<Q>(&mut self, k: &Q) -> Option<V>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.swap_remove_full(k).map(|(_, _, v)| v)
+    }
+
+    pub fn swap_remove_index(&mut self, index: I) -> Option<(K, V)> {
+        let index = index.usize();
+        if index >= self.len() { return None }
+
+        let idx = self.slot_of(index);
+        unsafe { self.table.remove_at(idx) }
+        return Some(self.swap_remove_finish(index));
+    }
+
+    /// removes the entry at `index`, after its slot was removed.
+    fn swap_remove_finish(&mut self, index: usize) -> (K, V) {
+        let last = self.len() - 1;
+        if index != last {
+            let idx = self.slot_of(last);
+            unsafe { self.table.set(idx, index as u32) }
+        }
+
+        let b = self.entries.remove_swap(unsafe { I::from_usize_unck(index) });
+        return (b.key, b.value);
+    }
+
+
+    /// removes the entry for `k`, and shifts the following entries down.
+    ///
+    /// - preserves the order, but `O(n)`.
+    pub fn shift_remove_full<Q>(&mut self, k: &Q) -> Option<(I, K, V)>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let (present, idx) = self.lookup(k, self.hash(k));
+        if !present { return None }
+
+        let index = self.table.get(idx) as usize;
+        unsafe { self.table.remove_at(idx) }
+        let (k, v) = self.shift_remove_finish(index);
+        return Some((unsafe { I::from_usize_unck(index) }, k, v));
+    }
+
+    #[inline]
+    pub fn shift_remove<Q>(&mut self, k: &Q) -> Option<V>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        self.shift_remove_full(k).map(|(_, _, v)| v)
+    }
+
+    pub fn shift_remove_index(&mut self, index: I) -> Option<(K, V)> {
+        let index = index.usize();
+        if index >= self.len() { return None }
+
+        let idx = self.slot_of(index);
+        unsafe { self.table.remove_at(idx) }
+        return Some(self.shift_remove_finish(index));
+    }
+
+    /// removes the entry at `index`, after its slot was removed.
+    fn shift_remove_finish(&mut self, index: usize) -> (K, V) {
+        if index + 1 < self.len() {
+            self.entries.as_mut_slice()[index..].rotate_left(1);
+            self.table.for_each_mut(|i| {
+                if *i as usize > index {
+                    *i -= 1;
+                }
+            });
+        }
+
+        let b = self.entries.pop().unwrap();
+        return (b.key, b.value);
+    }
+
+    /// removes the last entry.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        let last = self.len().checked_sub(1)?;
+        let idx = self.slot_of(last);
+        unsafe { self.table.remove_at(idx) }
+
+        let b = self.entries.pop().unwrap();
+        return Some((b.key, b.value));
+    }
+
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.rebuild_table(self.table.groups_num);
+    }
+
+    /// keeps only the entries, for which `f` returns `true`.
+    ///
+    /// - preserves the order.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        let len = self.len();
+        let entries = self.entries.as_mut_slice();
+
+        let mut kept = 0;
+        for i in 0..len {
+            let b = &mut entries[i];
+            if f(&b.key, &mut b.value) {
+                entries.swap(kept, i);
+                kept += 1;
+            }
+        }
+
+        if kept < len {
+            self.entries.truncate(kept);
+            self.rebuild_table(self.table.groups_num);
+        }
+    }
+
+
+    /// sorts the entries with a stable sort.
+    #[cfg(feature = "std")]
+    pub fn sort_by<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &V, &K, &V) -> crate::cmp::Ordering {
+        self.entries.sort_by(|a, b| f(&a.key, &a.value, &b.key, &b.value));
+        self.rebuild_table(self.table.groups_num);
+    }
+
+    pub fn sort_unstable_by<F>(&mut self, mut f: F)
+    where F: FnMut(&K, &V, &K, &V) -> crate::cmp::Ordering {
+        self.entries.sort_unstable_by(|a, b| f(&a.key, &a.value, &b.key, &b.value));
+        self.rebuild_table(self.table.groups_num);
+    }
+
+    /// sorts the entries by key.
+    ///
+    /// - keys are unique, so stability doesn't matter.
+    #[inline]
+    pub fn sort_keys(&mut self)
+    where K: Ord {
+        self.sort_unstable_by(|k1, _, k2, _| k1.cmp(k2));
+    }
+
+
+    pub fn clone_in<A2>(&self, alloc: A2) -> IndexMap<K, V, A2, H, I>
+    where K: Clone, V: Clone, H: Clone, A2: Alloc {
+        let mut result = IndexMap {
+            h: self.h.clone(),
+            entries: self.entries.clone_in(alloc),
+            table: IndexTable::new(),
+        };
+        result.rebuild_table(self.table.groups_num);
+        return result;
+    }
+
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { inner: self.entries.iter() }
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { inner: self.entries.iter_mut() }
+    }
+
+    /// like `iter`, but with the entries' indices.
+    #[inline]
+    pub fn kiter(&self) -> impl Iterator<Item = (I, &K, &V)> + '_ {
+        self.entries.kiter().map(|(i, b)| (i, &b.key, &b.value))
+    }
+
+    #[inline]
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> + ExactSizeIterator + '_ {
+        self.entries.iter().map(|b| &b.key)
+    }
+
+    #[inline]
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator + '_ {
+        self.entries.iter().map(|b| &b.value)
+    }
+
+    #[inline]
+    pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut V> + ExactSizeIterator + '_ {
+        self.entries.iter_mut().map(|b| &mut b.value)
+    }
+}
+
+
+// the table only holds `u32`s, owned by the map.
+unsafe impl<K: Send, V: Send, A: Alloc + Send, H: HashFn<K, u32> + Send, I: Key> Send for IndexMap<K, V, A, H, I> {}
+unsafe impl<K: Sync, V: Sync, A: Alloc + Sync, H: HashFn<K, u32> + Sync, I: Key> Sync for IndexMap<K, V, A, H, I> {}
+
+
+impl<K, V, A: Alloc, H: HashFn<K, u32>, I: Key> Drop for IndexMap<K, V, A, H, I> {
+    fn drop(&mut self) {
+        unsafe { self.table.free(self.entries.alloc()) }
+    }
+}
+
+
+impl<K: Clone, V: Clone, A: Alloc + Clone, H: HashFn<K, u32> + Clone, I: Key> Clone for IndexMap<K, V, A, H, I> {
+    fn clone(&self) -> Self {
+        return self.clone_in(self.alloc().clone());
+    }
+}
+
+
+impl<K, V, A: Alloc + Default, H: HashFn<K, u32> + Default, I: Key> Default for IndexMap<K, V, A, H, I> {
+    #[inline]
+    fn default() -> Self {
+        Self::with_hash_fn_in(A::default(), H::default())
+    }
+}
+
+
+impl<K: crate::fmt::Debug, V: crate::fmt::Debug, A: Alloc, H: HashFn<K, u32>, I: Key> crate::fmt::Debug for IndexMap<K, V, A, H, I> {
+    fn fmt(&self, f: &mut crate::fmt::Formatter) -> crate::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+
+impl<Q, K, V, A: Alloc, H: HashFn<K, u32>, I: Key> crate::ops::Index<&Q> for IndexMap<K, V, A, H, I>
+where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+    type Output = V;
+
+    #[inline]
+    fn index(&self, k: &Q) -> &Self::Output {
+        self.get(k).unwrap()
+    }
+}
+
+impl<Q, K, V, A: Alloc, H: HashFn<K, u32>, I: Key> crate::ops::IndexMut<&Q> for IndexMap<K, V, A, H, I>
+where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+    #[inline]
+    fn index_mut(&mut self, k: &Q) -> &mut Self::Output {
+        self.get_mut(k).unwrap()
+    }
+}
+
+
+impl<K: Hash + Eq, V, A: Alloc, I: Key, It: Iterator<Item = (K, V)>> FromIn<It, A> for IndexMap<K, V, A, FxHashFn, I> {
+    #[inline]
+    fn from_in(alloc: A, iter: It) -> Self {
+        let mut result = IndexMap::new_in(alloc);
+        result.extend(iter);
+        return result;
+    }
+}
+
+impl<K: Hash + Eq, V, I: Key> FromIterator<(K, V)> for IndexMap<K, V, GlobalAlloc, FxHashFn, I> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self::from_in(GlobalAlloc, iter.into_iter())
+    }
+}
+
+impl<K: Eq, V, A: Alloc, H: HashFn<K, u32>, I: Key> Extend<(K, V)> for IndexMap<K, V, A, H, I> {
+    #[inline]
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+
+        // the iterator may contain duplicates.
+        let (min_len, _) = iter.size_hint();
+        self.reserve(self.len() + min_len);
+
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K, V, A: Alloc, H: HashFn<K, u32>, I: Key> IntoIterator for IndexMap<K, V, A, H, I> {
+    type IntoIter = IntoIter<K, V, A, I>;
+    type Item = (K, V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        let mut map = crate::mem::ManuallyDrop::new(self);
+        let map = &mut *map;
+        unsafe {
+            map.table.free(map.entries.alloc());
+            drop(crate::ptr::read(&map.h));
+            return IntoIter { inner: crate::ptr::read(&map.entries).into_iter() };
+        }
+    }
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, u32>, I: Key> IntoIterator for &'a IndexMap<K, V, A, H, I> {
+    type IntoIter = Iter<'a, K, V>;
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        return self.iter();
+    }
+}
+
+impl<'a, K, V, A: Alloc, H: HashFn<K, u32>, I: Key> IntoIterator for &'a mut IndexMap<K, V, A, H, I> {
+    type IntoIter = IterMut<'a, K, V>;
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        return self.iter_mut();
+    }
+}
+
+
+pub struct Iter<'a, K, V> {
+    inner: core::slice::Iter<'a, Bucket<K, V>>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|b| (&b.key, &b.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|b| (&b.key, &b.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+
+pub struct IterMut<'a, K, V> {
+    inner: core::slice::IterMut<'a, Bucket<K, V>>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|b| (&b.key, &mut b.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|b| (&b.key, &mut b.value))
+    }
+}
+
+impl<'a, K, V> ExactSizeIterator for IterMut<'a, K, V> {}
+
+
+pub struct IntoIter<K, V, A: Alloc, I: Key> {
+    inner: crate::vec::IntoIter<I, Bucket<K, V>, A>,
+}
+
+impl<K, V, A: Alloc, I: Key> Iterator for IntoIter<K, V, A, I> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, b)| (b.key, b.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+
+
+/// the index table.
+///
+/// - same groups and probing as `HashMap`,
+///   but the slots only hold `u32` indices into the entries.
+/// - the hashes live in the entries, so the table is rebuilt from those.
+/// - doesn't store an allocator, the map passes in its own.
+struct IndexTable {
+    groups_num: u32,
+    empty: u32,
+    groups_ptr: NonNull<Group>,
+}
+
+impl IndexTable {
+    #[inline]
+    const fn new() -> Self {
+        Self { groups_num: 0, empty: 0, groups_ptr: NonNull::dangling() }
+    }
+
+    #[inline]
+    fn cap(&self) -> usize {
+        self.groups_num as usize * EMPTY_PER_GROUP
+    }
+
+    #[inline]
+    fn layout(groups_num: u32) -> Option<Layout> {
+        let groups_num: usize = groups_num.try_into().expect("unreachable");
+        let num_slots = groups_num.checked_mul(Group::WIDTH)?;
+        cat_join(
+            Layout::array::<Group>(groups_num).ok()?,
+            Layout::array::<u32>(num_slots).ok()?)
+    }
+
+    #[inline]
+    fn slots_ptr(&self) -> *mut u32 {
+        unsafe { cat_next_mut(self.groups_ptr.as_ptr(), self.groups_num as usize) }
+    }
+
+    #[inline]
+    fn group_mut(&mut self, idx: SlotIdx) -> &mut Group {
+        debug_assert!((idx.0 as usize) < self.groups_num as usize * Group::WIDTH);
+        unsafe { &mut *self.groups_ptr.as_ptr().add(idx.0 as usize / Group::WIDTH) }
+    }
+
+
+    /// like `HashMap::lookup_cmp`.
+    fn lookup(&self, hash: Hash32, cmp: impl Fn(u32) -> bool) -> (bool, SlotIdx) {
+        if self.groups_num == 0 {
+            return (false, SlotIdx(0));
+        }
+
+        let slots = self.slots_ptr();
+
+        let mut tomb = None;
+
+        let mut group_idx = u32::first_group(hash, self.groups_num as usize);
+        loop { unsafe {
+            let group = &*self.groups_ptr.as_ptr().add(group_idx);
+            let group_slots = slots.add(Group::WIDTH*group_idx);
+
+            for i in group.match_tag(u32::tag(hash)) {
+                if cmp(group_slots.add(i).read()) {
+                    return (true, SlotIdx((Group::WIDTH*group_idx + i) as u32));
+                }
+            }
+
+            if tomb.is_none() {
+                if let Some(i) = group.match_tomb().next() {
+                    tomb = Some(SlotIdx((Group::WIDTH*group_idx + i) as u32));
+                }
+            }
+
+            if let Some(i) = group.match_fresh().next() {
+                let idx = tomb.unwrap_or(SlotIdx((Group::WIDTH*group_idx + i) as u32));
+                return (false, idx);
+            }
+
+            group_idx += 1;
+            if group_idx == self.groups_num as usize {
+                group_idx = 0;
+            }
+        }}
+    }
+
+    #[inline]
+    fn get(&self, idx: SlotIdx) -> u32 {
+        debug_assert!((idx.0 as usize) < self.groups_num as usize * Group::WIDTH);
+        unsafe { self.slots_ptr().add(idx.0 as usize).read() }
+    }
+
+    /// # safety:
+    /// - `idx` must be a used slot.
+    #[inline]
+    unsafe fn set(&mut self, idx: SlotIdx, index: u32) {
+        debug_assert!(self.group_mut(idx).is_used(idx.0 as usize));
+        unsafe { self.slots_ptr().add(idx.0 as usize).write(index) }
+    }
+
+    /// # safety:
+    /// - `idx` must be a free slot.
+    /// - if it's fresh, `self.empty > 0`.
+    #[inline]
+    unsafe fn insert_at(&mut self, idx: SlotIdx, hash: Hash32, index: u32) {
+        let group = self.group_mut(idx);
+        debug_assert!(!group.is_used(idx.0 as usize));
+
+        let fresh = group.is_fresh(idx.0 as usize);
+        group.use_entry(idx.0 as usize, u32::tag(hash));
+        self.empty -= fresh as u32;
+
+        unsafe { self.slots_ptr().add(idx.0 as usize).write(index) }
+    }
+
+    /// # safety:
+    /// - `idx` must be a used slot.
+    #[inline]
+    unsafe fn remove_at(&mut self, idx: SlotIdx) {
+        let group = self.group_mut(idx);
+        debug_assert!(group.is_used(idx.0 as usize));
+        self.empty += group.free_entry(idx.0 as usize) as u32;
+    }
+
+    fn for_each_mut(&mut self, mut f: impl FnMut(&mut u32)) {
+        let slots = self.slots_ptr();
+        for group_idx in 0..self.groups_num as usize { unsafe {
+            let group = &*self.groups_ptr.as_ptr().add(group_idx);
+            for i in group.match_used() {
+                f(&mut *slots.add(Group::WIDTH*group_idx + i));
+            }
+        }}
+    }
+
+
+    /// resets the table to `groups_num` groups and inserts the entries.
+    ///
+    /// - `hashes` are the entries' hashes, in index order.
+    /// - only reallocates, if `groups_num` changed.
+    fn rebuild<A: Alloc>(&mut self, alloc: &A, groups_num: u32, hashes: impl Iterator<Item = Hash32>) {
+        if groups_num != self.groups_num {
+            let layout = Self::layout(groups_num).unwrap();
+            let data = alloc.alloc(layout).unwrap();
+            unsafe { self.free(alloc) }
+
+            self.groups_num = groups_num;
+            self.groups_ptr = data.cast();
+        }
+
+        for i in 0..groups_num as usize {
+            unsafe { self.groups_ptr.as_ptr().add(i).write(Group::fresh()) }
+        }
+        self.empty = (EMPTY_PER_GROUP * groups_num as usize) as u32;
+
+        for (index, hash) in hashes.enumerate() {
+            assert!(self.empty > 0);
+
+            let idx = {
+                let mut group_idx = u32::first_group(hash, self.groups_num as usize);
+                loop {
+                    let group = unsafe { &*self.groups_ptr.as_ptr().add(group_idx) };
+
+                    if let Some(i) = group.match_fresh().next() {
+                        break SlotIdx((Group::WIDTH*group_idx + i) as u32);
+                    }
+
+                    group_idx += 1;
+                    if group_idx == self.groups_num as usize {
+                        group_idx = 0;
+                    }
+                }
+            };
+
+            unsafe { self.insert_at(idx, hash, index as u32) }
+        }
+    }
+
+    /// # safety:
+    /// - `alloc` must be the allocator, the table was allocated with.
+    unsafe fn free<A: Alloc>(&mut self, alloc: &A) {
+        if self.groups_num == 0 { return }
+
+        unsafe {
+            let layout = Self::layout(self.groups_num).unwrap_unchecked();
+            alloc.free(self.groups_ptr.cast(), layout);
+        }
+
+        *self = Self::new();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::string::String;
+
+    #[test]
+    fn im_basic() {
+        let mut im: IndexMap<String, u32> = IndexMap::new();
+        assert!(im.is_empty());
+        assert!(im.get("a").is_none());
+        assert!(im.swap_remove("a").is_none());
+        assert!(im.pop().is_none());
+
+        assert_eq!(im.insert_full("c".into(), 3), (0, None));
+        assert_eq!(im.insert_full("a".into(), 1), (1, None));
+        assert_eq!(im.insert_full("b".into(), 2), (2, None));
+        assert_eq!(im.insert_full("a".into(), 10), (1, Some(1)));
+        assert_eq!(im.len(), 3);
+
+        assert_eq!(im["a"], 10);
+        assert_eq!(im.get_index_of("b"), Some(2));
+        assert_eq!(im.get_index(0).map(|(k, v)| (k.as_str(), *v)), Some(("c", 3)));
+        assert_eq!(im.get_full("c").map(|(i, _, v)| (i, *v)), Some((0, 3)));
+        assert!(im.get_index(3).is_none());
+
+        let keys: std::vec::Vec<&str> = im.keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, ["c", "a", "b"]);
+
+        im["c"] += 1;
+        *im.get_index_mut(2).unwrap().1 += 1;
+        let values: std::vec::Vec<u32> = im.values().copied().collect();
+        assert_eq!(values, [4, 10, 3]);
+
+        assert_eq!(im.first().map(|(k, _)| k.as_str()), Some("c"));
+        assert_eq!(im.last().map(|(k, _)| k.as_str()), Some("b"));
+        assert_eq!(im.pop(), Some((String::from("b"), 3)));
+        assert!(!im.contains_key("b"));
+        assert_eq!(im.len(), 2);
+
+        im.clear();
+        assert!(im.is_empty());
+        assert!(im.get("a").is_none());
+        im.insert("x".into(), 1);
+        assert_eq!(im.get_index_of("x"), Some(0));
+    }
+
+    #[test]
+    fn im_remove() {
+        let mut im: IndexMap<u32, u32> = (0..10).map(|i| (i, i*i)).collect();
+
+        // swap remove moves the last entry.
+        assert_eq!(im.swap_remove_full(&2), Some((2, 2, 4)));
+        assert_eq!(im.get_index_of(&9), Some(2));
+        assert_eq!(im.keys().copied().collect::<std::vec::Vec<_>>(), [0, 1, 9, 3, 4, 5, 6, 7, 8]);
+
+        // shift remove keeps the order.
+        assert_eq!(im.shift_remove_full(&1), Some((1, 1, 1)));
+        assert_eq!(im.keys().copied().collect::<std::vec::Vec<_>>(), [0, 9, 3, 4, 5, 6, 7, 8]);
+        for (i, k) in [0, 9, 3, 4, 5, 6, 7, 8].into_iter().enumerate() {
+            assert_eq!(im.get_index_of(&k), Some(i as u32));
+            assert_eq!(im[&k], k*k);
+        }
+
+        assert_eq!(im.swap_remove_index(0), Some((0, 0)));
+        assert_eq!(im.shift_remove_index(0), Some((8, 64)));
+        assert_eq!(im.shift_remove_index(6), None);
+        assert_eq!(im.shift_remove(&7), Some(49));
+        assert_eq!(im.swap_remove(&7), None);
+        assert_eq!(im.keys().copied().collect::<std::vec::Vec<_>>(), [9, 3, 4, 5, 6]);
+
+        im.retain(|k, v| { *v += 1; k % 2 == 1 });
+        assert_eq!(im.iter().map(|(k, v)| (*k, *v)).collect::<std::vec::Vec<_>>(), [(9, 82), (3, 10), (5, 26)]);
+        assert_eq!(im.get_index_of(&5), Some(2));
+        assert!(im.get(&4).is_none());
+
+        // lots of churn, to exercise tombstones and table rebuilds.
+        let mut im: IndexMap<u32, u32> = IndexMap::new();
+        let mut reference = std::vec::Vec::new();
+        for i in 0..2000u32 {
+            im.insert(i, i);
+            reference.push(i);
+            if i % 3 == 0 {
+                let k = reference[(i as usize * 7) % reference.len()];
+                if i % 2 == 0 {
+                    im.shift_remove(&k).unwrap();
+                    reference.retain(|x| *x != k);
+                }
+                else {
+                    im.swap_remove(&k).unwrap();
+                    let idx = reference.iter().position(|x| *x == k).unwrap();
+                    reference.swap_remove(idx);
+                }
+            }
+        }
+        assert_eq!(im.keys().copied().collect::<std::vec::Vec<_>>(), reference);
+        for (i, k) in reference.iter().enumerate() {
+            assert_eq!(im.get_index_of(k), Some(i as u32));
+        }
+    }
+
+    #[test]
+    fn im_sort_and_iter() {
+        let mut im: IndexMap<&str, u32> = IndexMap::new();
+        for (i, k) in ["d", "b", "e", "a", "c"].into_iter().enumerate() {
+            im.insert(k, i as u32 % 2);
+        }
+
+        im.sort_keys();
+        assert_eq!(im.keys().copied().collect::<std::vec::Vec<_>>(), ["a", "b", "c", "d", "e"]);
+        assert_eq!(im.get_index_of("d"), Some(3));
+
+        // stable: equal values keep the key order.
+        im.sort_by(|_, v1, _, v2| v1.cmp(v2));
+        assert_eq!(im.keys().copied().collect::<std::vec::Vec<_>>(), ["c", "d", "e", "a", "b"]);
+        assert_eq!(im.get_index_of("e"), Some(2));
+        assert_eq!(im.get_index_of("a"), Some(3));
+
+        im.sort_unstable_by(|k1, _, k2, _| k2.cmp(k1));
+        assert_eq!(im.keys().copied().collect::<std::vec::Vec<_>>(), ["e", "d", "c", "b", "a"]);
+
+        for (_, v) in &mut im {
+            *v += 10;
+        }
+        assert_eq!(im.iter().rev().map(|(k, v)| (*k, *v)).next(), Some(("a", 11)));
+        assert_eq!(im.kiter().map(|(i, k, _)| (i, *k)).nth(1), Some((1, "d")));
+
+        let cloned = im.clone();
+        assert_eq!(cloned.get_index_of("b"), Some(3));
+        assert_eq!(std::format!("{:?}", cloned), r#"{"e": 10, "d": 10, "c": 10, "b": 11, "a": 11}"#);
+
+        let owned: std::vec::Vec<(&str, u32)> = im.into_iter().collect();
+        assert_eq!(owned[0], ("e", 10));
+        assert_eq!(owned.len(), 5);
+    }
+
+    #[test]
+    fn im_send_sync() {
+        fn is_send<T: Send>() {}
+        fn is_sync<T: Sync>() {}
+        is_send::<IndexMap<u32, u32>>();
+        is_sync::<IndexMap<u32, u32>>();
+        is_send::<IndexMap<crate::string::String, u32>>();
+    }
+
+    #[test]
+    fn im_typed_index() {
+        crate::define_key!(SymbolId(u32));
+
+        let mut symbols: IndexMap<String, u32, GlobalAlloc, FxHashFn, SymbolId> = IndexMap::new();
+        let (foo, _) = symbols.insert_full("foo".into(), 1);
+        let (bar, _) = symbols.insert_full("bar".into(), 2);
+        assert_eq!(foo, SymbolId(0));
+        assert_eq!(bar, SymbolId(1));
+        assert_eq!(symbols.get_index_of("bar"), Some(bar));
+        assert_eq!(symbols.get_index(bar).map(|(k, _)| k.as_str()), Some("bar"));
+        assert_eq!(symbols.klen(), SymbolId(2));
+        assert_eq!(symbols.swap_remove_index(foo).map(|(_, v)| v), Some(1));
+        assert_eq!(symbols.get_index_of("bar"), Some(foo));
+    }
+}
@@ -7,8 +7,40 @@ pub trait HashFn<T: ?Sized, Hash> {
 
 
 pub mod fxhash;
+pub mod siphash;
+pub mod wyhash;
+
+
+/// a random seed, that's fixed for the lifetime of the process.
+///
+/// - for keyed hash functions, like `siphash::SipHashFn`.
+/// - taken from the std lib's `RandomState`.
+#[cfg(feature = "std")]
+pub fn process_seed() -> (u64, u64) {
+    use std::hash::BuildHasher;
+
+    static SEED: std::sync::OnceLock<(u64, u64)> = std::sync::OnceLock::new();
+
+    *SEED.get_or_init(|| {
+        let state = std::collections::hash_map::RandomState::new();
+        let k0 = state.hash_one(0u64);
+        let k1 = state.hash_one(1u64);
+        (k0, k1)
+    })
+}
 
 
 pub mod hash_map;
 pub use hash_map::HashMap;
 
+
+pub mod hash_set;
+pub use hash_set::HashSet;
+
+
+pub mod index_map;
+pub use index_map::IndexMap;
+
+
+pub mod sharded_hash_map;
+pub use sharded_hash_map::ShardedHashMap;
@@ -0,0 +1,356 @@
+use crate::borrow::Borrow;
+use crate::alloc::{Alloc, GlobalAlloc};
+use crate::vec::Vec;
+use crate::sync::rwlock::RwLock;
+use crate::hash::{Hash, HashFn, fxhash::FxHashFn};
+use crate::hash::hash_map::{HashMap, HashWidth, Hash32, Entry};
+
+
+/// the map of a single shard.
+pub type Shard<K, V, A = GlobalAlloc, H = FxHashFn> = HashMap<K, V, A, ShardHashFn<H>>;
+
+
+/// a concurrent hash map.
+///
+/// - a power of two number of `HashMap`s ("shards"), each behind a `RwLock`.
+/// - keys are routed to shards by the high bits of their hash,
+///   so operations on different shards don't contend.
+/// - operations lock at most one shard at a time.
+///   `retain` & co visit the shards in order,
+///   so they don't see a consistent snapshot.
+pub struct ShardedHashMap<K, V, A: Alloc = GlobalAlloc, H: HashFn<K, u32> = FxHashFn> {
+    h: H,
+    shard_bits: u32,
+    shards: Vec<Padded<RwLock<Shard<K, V, A, H>>>, A>,
+}
+
+impl<K: Hash, V> ShardedHashMap<K, V, GlobalAlloc, FxHashFn> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    #[inline]
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_in(GlobalAlloc, shards)
+    }
+}
+
+impl<K: Hash, V, A: Alloc + Clone> ShardedHashMap<K, V, A, FxHashFn> {
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        Self::with_shards_in(alloc, DEFAULT_SHARDS)
+    }
+
+    #[inline]
+    pub fn with_shards_in(alloc: A, shards: usize) -> Self {
+        Self::with_hash_fn_in(alloc, FxHashFn, shards)
+    }
+}
+
+impl<K, V, A: Alloc + Clone, H: HashFn<K, u32> + Clone> ShardedHashMap<K, V, A, H> {
+    /// - `shards` is rounded up to a power of two.
+    pub fn with_hash_fn_in(alloc: A, h: H, shards: usize) -> Self {
+        assert!(shards > 0 && shards <= MAX_SHARDS);
+
+        let shards_num = shards.next_power_of_two();
+        let shard_bits = shards_num.trailing_zeros();
+
+        let mut shards = Vec::with_cap_in(alloc.clone(), shards_num);
+        for _ in 0..shards_num {
+            let h = ShardHashFn { h: h.clone(), shard_bits };
+            shards.push(Padded(RwLock::new(HashMap::with_hash_fn_in(alloc.clone(), h))));
+        }
+
+        return Self { h, shard_bits, shards };
+    }
+}
+
+impl<K, V, A: Alloc, H: HashFn<K, u32>> ShardedHashMap<K, V, A, H> {
+    #[inline]
+    pub fn hash_fn(&self) -> &H {
+        &self.h
+    }
+
+    #[inline]
+    pub fn shards_num(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// the shard for `hash`.
+    #[inline]
+    pub fn shard_idx(&self, hash: Hash32) -> usize {
+        u32::first_group(hash, self.shards.len())
+    }
+
+    /// for operations, that need the shard's map.
+    ///
+    /// - the map's hashes are `ShardHashFn::shard_hash` of `self.hash`.
+    #[inline]
+    pub fn shard(&self, idx: usize) -> &RwLock<Shard<K, V, A, H>> {
+        &self.shards.as_slice()[idx].0
+    }
+
+    /// the total number of entries.
+    ///
+    /// - locks the shards one by one,
+    ///   so the result may be outdated.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.0.read().len()).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+
+    #[inline(always)]
+    pub fn hash<Q>(&self, k: &Q) -> Hash32
+    where Q: ?Sized, K: Borrow<Q>, H: HashFn<Q, u32> {
+        Hash32(self.h.hash(k))
+    }
+
+    /// the shard and the shard map's hash for `hash`.
+    #[inline(always)]
+    fn route(&self, hash: Hash32) -> (&RwLock<Shard<K, V, A, H>>, Hash32) {
+        let shard = self.shard(self.shard_idx(hash));
+        return (shard, Hash32(shard_hash(hash.0, self.shard_bits)));
+    }
+
+
+    pub fn get_cloned<Q>(&self, k: &Q) -> Option<V>
+    where V: Clone, Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let (shard, hash) = self.route(self.hash(k));
+        let shard = shard.read();
+        let (present, idx) = shard.lookup(k, hash);
+        if present {
+            Some(unsafe { shard.slot_unck(idx).1.clone() })
+        }
+        else { None }
+    }
+
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let (shard, hash) = self.route(self.hash(k));
+        return shard.read().lookup(k, hash).0;
+    }
+
+    pub fn insert(&self, k: K, v: V) -> Option<(K, V)>
+    where K: Eq {
+        let (shard, hash) = self.route(self.hash(&k));
+        let mut shard = shard.write();
+        let (_, idx) = shard.lookup_for_insert(&k, hash);
+        return unsafe { shard.insert_at_unck(idx, hash, k, v) };
+    }
+
+    pub fn remove<Q>(&self, k: &Q) -> Option<(K, V)>
+    where Q: ?Sized + Eq, K: Borrow<Q>, H: HashFn<Q, u32> {
+        let (shard, hash) = self.route(self.hash(k));
+        let mut shard = shard.write();
+        let (present, idx) = shard.lookup(k, hash);
+        if present { Some(unsafe { shard.remove_at_unck(idx) }) }
+        else { None }
+    }
+
+    /// calls `f` with the entry for `k`.
+    ///
+    /// - `k`'s shard is write locked, while `f` runs.
+    ///   so `f` must not access the map.
+    pub fn entry<R, F>(&self, k: K, f: F) -> R
+    where K: Eq, F: FnOnce(Entry<'_, K, V, A, ShardHashFn<H>>) -> R {
+        let (shard, hash) = self.route(self.hash(&k));
+        let mut shard = shard.write();
+        return f(shard.kentry_with_hash(k, hash));
+    }
+
+    /// keeps only the entries, for which `f` returns `true`.
+    ///
+    /// - write locks one shard at a time.
+    ///   the current shard is locked, while `f` runs.
+    ///   so `f` must not access the map.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&self, mut f: F) {
+        for shard in self.shards.iter() {
+            shard.0.write().retain(&mut f);
+        }
+    }
+
+    /// - write locks one shard at a time.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.0.write().clear();
+        }
+    }
+}
+
+
+impl<K, V, A: Alloc + Clone + Default, H: HashFn<K, u32> + Clone + Default> Default for ShardedHashMap<K, V, A, H> {
+    #[inline]
+    fn default() -> Self {
+        Self::with_hash_fn_in(A::default(), H::default(), DEFAULT_SHARDS)
+    }
+}
+
+
+pub const DEFAULT_SHARDS: usize = 64;
+
+/// the shard hash keeps `32 - shard_bits` bits of the hash,
+/// which must cover the tag and some group bits.
+pub const MAX_SHARDS: usize = 1 << 12;
+
+
+/// the hash function of the shards' maps.
+#[derive(Clone)]
+pub struct ShardHashFn<H> {
+    h: H,
+    shard_bits: u32,
+}
+
+impl<H> ShardHashFn<H> {
+    #[inline(always)]
+    pub fn shard_hash(&self, hash: Hash32) -> Hash32 {
+        Hash32(shard_hash(hash.0, self.shard_bits))
+    }
+}
+
+impl<T: ?Sized, H: HashFn<T, u32>> HashFn<T, u32> for ShardHashFn<H> {
+    #[inline(always)]
+    fn hash(&self, value: &T) -> u32 {
+        shard_hash(self.h.hash(value), self.shard_bits)
+    }
+}
+
+/// the routing bits are the same for all keys in a shard.
+/// the shard's map uses the high bits for the first group,
+/// so we shift them out. otherwise, the keys would start
+/// probing in only `1/shards_num` of the groups.
+/// the vacated low bits (the tag) are refilled from bits `7..`.
+#[inline(always)]
+fn shard_hash(hash: u32, shard_bits: u32) -> u32 {
+    let mask = (1 << shard_bits) - 1;
+    return (hash << shard_bits) | ((hash >> 7) & mask);
+}
+
+
+/// keeps the shards' locks on separate cache lines.
+#[repr(align(64))]
+struct Padded<T>(T);
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::hash_map::Group;
+    use crate::string::String;
+
+    #[test]
+    fn shm_basic() {
+        let map: ShardedHashMap<u32, String> = ShardedHashMap::with_shards(5);
+        assert_eq!(map.shards_num(), 8);
+        assert!(map.is_empty());
+
+        for i in 0..1000 {
+            assert_eq!(map.insert(i, i.to_string().as_str().into()), None);
+        }
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.get_cloned(&7).unwrap(), "7");
+        assert_eq!(map.get_cloned(&1000), None);
+        assert!(map.contains_key(&999));
+
+        let (k, v) = map.insert(7, "seven".into()).unwrap();
+        assert_eq!((k, v.as_str()), (7, "7"));
+        assert_eq!(map.get_cloned(&7).unwrap(), "seven");
+
+        let (_, v) = map.remove(&7).unwrap();
+        assert_eq!(v, "seven");
+        assert_eq!(map.remove(&7), None);
+        assert_eq!(map.len(), 999);
+
+        let len = map.entry(7, |e| e.or_insert_with(|| "new".into()).len());
+        assert_eq!(len, 3);
+        map.entry(7, |e| { e.and_modify(|v| v.push("er")); });
+        assert_eq!(map.get_cloned(&7).unwrap(), "newer");
+        let old = map.entry(8, |e| match e {
+            Entry::Occupied(e) => e.remove(),
+            Entry::Vacant(_) => unreachable!(),
+        });
+        assert_eq!(old, "8");
+
+        map.retain(|k, _| k % 2 == 1);
+        assert_eq!(map.len(), 500);
+        assert_eq!(map.get_cloned(&7).unwrap(), "newer");
+        assert!(!map.contains_key(&6));
+
+        // each key is in its hash's shard.
+        for idx in 0..map.shards_num() {
+            for (k, _) in map.shard(idx).read().iter() {
+                assert_eq!(map.shard_idx(map.hash(k)), idx);
+            }
+        }
+
+        map.clear();
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn shm_distribution() {
+        let map: ShardedHashMap<u32, u32> = ShardedHashMap::with_shards(16);
+        let n = 16*1000;
+        for i in 0..n {
+            map.insert(i, i);
+        }
+
+        for idx in 0..map.shards_num() {
+            let shard = map.shard(idx).read();
+            let len = shard.len() as u32;
+            assert!(len > 800 && len < 1200, "{len}");
+
+            // within a shard, the keys use all the groups.
+            let groups_num = shard.size() / Group::WIDTH;
+            let mut starts = std::vec![0u32; groups_num];
+            for (k, _) in shard.iter() {
+                starts[u32::first_group(shard.hash(k), groups_num)] += 1;
+            }
+            let unused = starts.iter().filter(|c| **c == 0).count();
+            assert!(unused < groups_num / 8, "{unused}/{groups_num}");
+        }
+
+        let h = Hash32(0x8000_0000 | 0x7f);
+        assert_eq!(map.shard_idx(h), 8);
+        assert_eq!(shard_hash(h.0, 4) & 0x7f, 0x7f & !0xf);
+    }
+
+    #[test]
+    fn shm_threads() {
+        let map: ShardedHashMap<u32, u32> = ShardedHashMap::new();
+
+        std::thread::scope(|s| {
+            for t in 0..8 {
+                let map = &map;
+                s.spawn(move || {
+                    // shared counters.
+                    for i in 0..1000 {
+                        map.entry(i % 100, |e| *e.or_default() += 1);
+                    }
+                    // per thread keys.
+                    for i in 0..1000 {
+                        map.insert(1000 + t*1000 + i, t);
+                    }
+                    for i in (0..1000).step_by(2) {
+                        assert_eq!(map.remove(&(1000 + t*1000 + i)), Some((1000 + t*1000 + i, t)));
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), 100 + 8*500);
+        for i in 0..100 {
+            assert_eq!(map.get_cloned(&i), Some(80));
+        }
+        for t in 0..8 {
+            assert_eq!(map.get_cloned(&(1000 + t*1000 + 1)), Some(t));
+            assert_eq!(map.get_cloned(&(1000 + t*1000)), None);
+        }
+    }
+}
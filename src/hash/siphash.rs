@@ -0,0 +1,283 @@
+use crate::hash::{Hash, Hasher, HashFn};
+
+
+/// siphash with `C` compression and `D` finalization rounds.
+///
+/// - https://www.aumasson.jp/siphash/siphash.pdf
+/// - keyed, so collisions can't be precomputed,
+///   if the key is secret. see `hash::process_seed`.
+/// - writes are buffered, so the result only depends on the
+///   concatenated bytes. (integers are written in native endian.)
+#[derive(Clone, Debug)]
+pub struct SipHasher<const C: usize, const D: usize> {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+/// the variant used by the rust std lib.
+/// faster than `SipHasher24`, still considered safe for hash tables.
+pub type SipHasher13 = SipHasher<1, 3>;
+
+/// the reference variant.
+pub type SipHasher24 = SipHasher<2, 4>;
+
+impl<const C: usize, const D: usize> SipHasher<C, D> {
+    #[inline]
+    pub const fn new_with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            v0: k0 ^ 0x736f6d6570736575,
+            v1: k1 ^ 0x646f72616e646f6d,
+            v2: k0 ^ 0x6c7967656e657261,
+            v3: k1 ^ 0x7465646279746573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13);
+        self.v1 ^= self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16);
+        self.v3 ^= self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21);
+        self.v3 ^= self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17);
+        self.v1 ^= self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    #[inline(always)]
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        for _ in 0..C {
+            self.round();
+        }
+        self.v0 ^= m;
+    }
+
+
+    pub fn write_bytes(&mut self, mut bytes: &[u8]) {
+        self.length += bytes.len();
+
+        // fill the tail.
+        if self.ntail != 0 {
+            let n = bytes.len().min(8 - self.ntail);
+            self.tail |= load_le(&bytes[..n]) << (8*self.ntail);
+            self.ntail += n;
+            bytes = &bytes[n..];
+
+            if self.ntail < 8 {
+                return;
+            }
+            self.compress(self.tail);
+            self.tail = 0;
+            self.ntail = 0;
+        }
+
+        while bytes.len() >= 8 {
+            let m = u64::from_le_bytes(bytes[..8].try_into().expect("unreachable"));
+            self.compress(m);
+            bytes = &bytes[8..];
+        }
+
+        self.tail = load_le(bytes);
+        self.ntail = bytes.len();
+    }
+
+    pub fn finish_u64(&self) -> u64 {
+        let mut state = self.clone();
+
+        let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(b);
+
+        state.v2 ^= 0xff;
+        for _ in 0..D {
+            state.round();
+        }
+
+        return state.v0 ^ state.v1 ^ state.v2 ^ state.v3;
+    }
+
+    #[inline]
+    pub fn finish_u32(&self) -> u32 {
+        let hash = self.finish_u64();
+        return (hash ^ (hash >> 32)) as u32;
+    }
+}
+
+/// loads up to 8 bytes as a little endian integer.
+#[inline(always)]
+fn load_le(bytes: &[u8]) -> u64 {
+    debug_assert!(bytes.len() <= 8);
+    let mut buffer = [0; 8];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    return u64::from_le_bytes(buffer);
+}
+
+impl<const C: usize, const D: usize> Hasher for SipHasher<C, D> {
+    #[inline(always)]
+    fn finish(&self) -> u64 {
+        self.finish_u64()
+    }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.write_bytes(bytes);
+    }
+}
+
+
+#[inline]
+pub fn siphash13<T: Hash + ?Sized>(k0: u64, k1: u64, value: &T) -> u64 {
+    let mut hasher = SipHasher13::new_with_keys(k0, k1);
+    value.hash(&mut hasher);
+    hasher.finish_u64()
+}
+
+
+/// keyed siphash-1-3.
+///
+/// - for hash maps with untrusted keys.
+/// - `default` (with `std`) uses the random `hash::process_seed`.
+#[derive(Clone, Copy, Debug)]
+pub struct SipHashFn {
+    pub k0: u64,
+    pub k1: u64,
+}
+
+impl SipHashFn {
+    #[inline(always)]
+    pub const fn new(k0: u64, k1: u64) -> Self { Self { k0, k1 } }
+
+    /// keyed with `hash::process_seed`.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn random() -> Self {
+        let (k0, k1) = crate::hash::process_seed();
+        Self { k0, k1 }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SipHashFn {
+    #[inline]
+    fn default() -> Self { Self::random() }
+}
+
+impl<T: Hash + ?Sized> HashFn<T, u32> for SipHashFn {
+    #[inline]
+    fn hash(&self, value: &T) -> u32 {
+        let mut hasher = SipHasher13::new_with_keys(self.k0, self.k1);
+        value.hash(&mut hasher);
+        hasher.finish_u32()
+    }
+}
+
+impl<T: Hash + ?Sized> HashFn<T, u64> for SipHashFn {
+    #[inline]
+    fn hash(&self, value: &T) -> u64 {
+        siphash13(self.k0, self.k1, value)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::HashMap;
+    use crate::alloc::GlobalAlloc;
+
+    #[test]
+    fn siphash_reference() {
+        // reference vectors from the paper's implementation.
+        // key = 00..0f, message = 00..(n-1).
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let message: std::vec::Vec<u8> = (0..64).collect();
+
+        let hash = |n: usize| {
+            let mut h = SipHasher24::new_with_keys(k0, k1);
+            h.write_bytes(&message[..n]);
+            h.finish_u64()
+        };
+        assert_eq!(hash(0),  0x726fdb47dd0e0e31);
+        assert_eq!(hash(1),  0x74f839c593dc67fd);
+        assert_eq!(hash(63), 0x958a324ceb064572);
+    }
+
+    #[test]
+    fn siphash_vectors() {
+        // siphash-2-4 vectors from the paper's implementation,
+        // siphash-1-3 vectors from rust's libcore tests.
+        // key = 00..0f, message = 00..(n-1).
+        let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+        let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+        let message: std::vec::Vec<u8> = (0..64).collect();
+
+        let vectors_24 = [
+            (0,  0x726fdb47dd0e0e31),
+            (1,  0x74f839c593dc67fd),
+            (7,  0xab0200f58b01d137),
+            (8,  0x93f5f5799a932462),
+            (15, 0xa129ca6149be45e5),
+            (16, 0x3f2acc7f57c29bdb),
+            (63, 0x958a324ceb064572),
+        ];
+        let vectors_13 = [
+            (0,  0xabac0158050fc4dc),
+            (1,  0xc9f49bf37d57ca93),
+            (7,  0xd3927d989bb11140),
+            (8,  0x369095118d299a8e),
+            (15, 0xd320d86d2a519956),
+            (16, 0xcc4fdd1a7d908b66),
+            (63, 0x9d199062b7bbb3a8),
+        ];
+
+        // split writes, to test the tail handling.
+        for (n, expected) in vectors_24 {
+            for split in 0..=n {
+                let mut h = SipHasher24::new_with_keys(k0, k1);
+                h.write(&message[..split]);
+                h.write(&message[split..n]);
+                assert_eq!(h.finish(), expected);
+            }
+        }
+        for (n, expected) in vectors_13 {
+            for split in 0..=n {
+                let mut h = SipHasher13::new_with_keys(k0, k1);
+                h.write(&message[..split]);
+                h.write(&message[split..n]);
+                assert_eq!(h.finish(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn siphash_map() {
+        let h = SipHashFn::new(1, 2);
+        assert_ne!(HashFn::<str, u64>::hash(&h, "a"), HashFn::<str, u64>::hash(&SipHashFn::new(2, 1), "a"));
+
+        let mut hm: HashMap<&str, u32, GlobalAlloc, SipHashFn> = HashMap::with_hash_fn_in(GlobalAlloc, h);
+        hm.insert("content-type", 1);
+        hm.insert("content-length", 2);
+        assert_eq!(hm.get("content-type"), Some(&1));
+        assert_eq!(hm.get("content-length"), Some(&2));
+
+        let random: HashMap<&str, u32, GlobalAlloc, SipHashFn> = HashMap::default();
+        assert_eq!(random.hash_fn().k0, crate::hash::process_seed().0);
+        assert_eq!(crate::hash::process_seed(), crate::hash::process_seed());
+    }
+}
@@ -0,0 +1,330 @@
+use crate::hash::{Hash, Hasher, HashFn};
+
+
+// the wyhash "secret".
+// odd, with 32 bits set, and pairwise hamming distance 32.
+const P0: u64 = 0xa0761d6478bd642f;
+const P1: u64 = 0xe7037ed1a0b428db;
+const P2: u64 = 0x8ebc6af09c88c6e3;
+const P3: u64 = 0x589965cc75374cc3;
+
+/// full 64x64 -> 128 bit multiply, returns (lo, hi).
+#[inline(always)]
+fn mum(a: u64, b: u64) -> (u64, u64) {
+    let r = a as u128 * b as u128;
+    return (r as u64, (r >> 64) as u64);
+}
+
+/// folded multiply.
+/// every input bit affects the high *and* low output bits.
+#[inline(always)]
+fn mix(a: u64, b: u64) -> u64 {
+    let (lo, hi) = mum(a, b);
+    return lo ^ hi;
+}
+
+#[inline(always)]
+fn read8(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at+8].try_into().expect("unreachable"))
+}
+
+#[inline(always)]
+fn read4(bytes: &[u8], at: usize) -> u64 {
+    u32::from_le_bytes(bytes[at..at+4].try_into().expect("unreachable")) as u64
+}
+
+
+/// wyhash of `bytes`.
+///
+/// - https://github.com/wangyi-fudan/wyhash
+/// - passes the upstream test vectors, none of which
+///   has a length that's a multiple of 48.
+/// - consumes 16 bytes per round (48 for long inputs),
+///   with one wide multiply per 8 bytes.
+#[inline]
+pub fn wyhash_bytes(bytes: &[u8], seed: u64) -> u64 {
+    return hash_bytes(bytes, seed ^ mix(seed ^ P0, P1));
+}
+
+/// `wyhash_bytes` without the seed scrambling.
+/// for the streaming hasher, where the seed is already scrambled.
+#[inline]
+fn hash_bytes(bytes: &[u8], mut seed: u64) -> u64 {
+    let len = bytes.len();
+
+    let a;
+    let b;
+    if len <= 16 {
+        if len >= 4 {
+            let d = (len >> 3) << 2;
+            a = (read4(bytes, 0) << 32) | read4(bytes, d);
+            b = (read4(bytes, len-4) << 32) | read4(bytes, len-4-d);
+        }
+        else if len > 0 {
+            a =   ((bytes[0]       as u64) << 16)
+                | ((bytes[len / 2] as u64) <<  8)
+                |  (bytes[len - 1] as u64);
+            b = 0;
+        }
+        else {
+            a = 0;
+            b = 0;
+        }
+    }
+    else {
+        let mut at = 0;
+        let mut rem = len;
+
+        if rem >= 48 {
+            let mut see1 = seed;
+            let mut see2 = seed;
+            while rem >= 48 {
+                seed = mix(read8(bytes, at)      ^ P1, read8(bytes, at +  8) ^ seed);
+                see1 = mix(read8(bytes, at + 16) ^ P2, read8(bytes, at + 24) ^ see1);
+                see2 = mix(read8(bytes, at + 32) ^ P3, read8(bytes, at + 40) ^ see2);
+                at  += 48;
+                rem -= 48;
+            }
+            seed ^= see1 ^ see2;
+        }
+
+        while rem > 16 {
+            seed = mix(read8(bytes, at) ^ P1, read8(bytes, at + 8) ^ seed);
+            at  += 16;
+            rem -= 16;
+        }
+
+        // the last 16 bytes, may overlap with the previous round.
+        a = read8(bytes, len - 16);
+        b = read8(bytes, len -  8);
+    }
+
+    let (a, b) = mum(a ^ P1, b ^ seed);
+    return mix(a ^ P0 ^ len as u64, b ^ P1);
+}
+
+
+#[inline]
+pub fn wyhash32<T: Hash + ?Sized>(value: &T) -> u32 {
+    let mut hasher = WyHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish_u32()
+}
+
+#[inline]
+pub fn wyhash64<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = WyHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish_u64()
+}
+
+
+/// streaming wyhash.
+///
+/// - each `write` is hashed like `wyhash_bytes`,
+///   using the current state as the seed.
+/// - integers are mixed in with a single folded multiply,
+///   `finish` adds one more.
+/// - several times faster than fxhash on long strings
+///   (break even around 64 bytes), but slower on short keys.
+///   see the `wyhash_bench` test.
+/// - not keyed, so not a defense against collision attacks.
+///   use `SipHashFn` for untrusted keys.
+pub struct WyHasher {
+    pub hash: u64,
+}
+
+impl WyHasher {
+    pub const DEFAULT_SEED: u64 = 0;
+
+
+    #[inline(always)]
+    pub fn new() -> Self { Self::from_seed(Self::DEFAULT_SEED) }
+
+    #[inline(always)]
+    pub fn from_seed(seed: u64) -> Self { Self { hash: seed ^ P2 } }
+
+
+    #[inline(always)]
+    pub fn finish_u64(&self) -> u64 {
+        // one multiply per integer isn't enough for inputs
+        // that only differ in their high bits.
+        mix(self.hash ^ P3, P1)
+    }
+
+    #[inline(always)]
+    pub fn finish_u32(&self) -> u32 {
+        let hash = self.finish_u64();
+        (hash ^ (hash >> 32)) as u32
+    }
+
+
+    #[inline]
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.hash = hash_bytes(bytes, self.hash);
+    }
+
+    #[inline(always)]
+    fn add_u64(&mut self, value: u64) {
+        self.hash = mix(value ^ P1, self.hash ^ P0);
+    }
+}
+
+impl Hasher for WyHasher {
+    #[inline(always)]
+    fn finish(&self) -> u64 { self.finish_u64() }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.write_bytes(bytes);
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.add_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.add_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.add_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.add_u64(i);
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.add_u64((i >>  0) as u64);
+        self.add_u64((i >> 64) as u64);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.add_u64(i as u64);
+    }
+}
+
+
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WyHashFn;
+
+impl<T: Hash + ?Sized> HashFn<T, u32> for WyHashFn {
+    #[inline(always)]
+    fn hash(&self, value: &T) -> u32 {
+        wyhash32(value)
+    }
+}
+
+impl<T: Hash + ?Sized> HashFn<T, u64> for WyHashFn {
+    #[inline(always)]
+    fn hash(&self, value: &T) -> u64 {
+        wyhash64(value)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wyhash_basic() {
+        // all input lengths take a different path.
+        let bytes: std::vec::Vec<u8> = (0..200).map(|i| (i*13 + 7) as u8).collect();
+        let mut seen = std::collections::HashSet::new();
+        for n in 0..bytes.len() {
+            assert!(seen.insert(wyhash_bytes(&bytes[..n], 0)));
+        }
+
+        // seed matters.
+        assert_ne!(wyhash_bytes(b"hello", 0), wyhash_bytes(b"hello", 1));
+
+        // write boundaries matter. (`str` appends a terminator.)
+        assert_ne!(wyhash64(&("ab", "c")), wyhash64(&("a", "bc")));
+
+        // upstream test vectors, seeded with the index.
+        let vectors: [(&str, u64); 7] = [
+            ("", 0x0409638ee2bde459),
+            ("a", 0xa8412d091b5fe0a9),
+            ("abc", 0x32dd92e4b2915153),
+            ("message digest", 0x8619124089a3a16b),
+            ("abcdefghijklmnopqrstuvwxyz", 0x7a43afb61d7f5f40),
+            ("ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789", 0xff42329b90e50d58),
+            ("12345678901234567890123456789012345678901234567890123456789012345678901234567890", 0xc39cab13b115aad3),
+        ];
+        for (i, (input, expected)) in vectors.iter().enumerate() {
+            assert_eq!(wyhash_bytes(input.as_bytes(), i as u64), *expected, "{input:?}");
+        }
+
+        assert_eq!(wyhash64("hi"), HashFn::<str, u64>::hash(&WyHashFn, "hi"));
+        assert_eq!(wyhash32("hi"), HashFn::<str, u32>::hash(&WyHashFn, "hi"));
+    }
+
+    #[test]
+    fn wyhash_avalanche() {
+        // flipping any input bit should flip about half the output bits.
+        let check = |input: &[u8]| {
+            let base = wyhash_bytes(input, 0);
+            let mut total = 0;
+            let mut input = input.to_vec();
+            for bit in 0..input.len()*8 {
+                input[bit/8] ^= 1 << (bit%8);
+                let flipped = (wyhash_bytes(&input, 0) ^ base).count_ones();
+                assert!(flipped >= 12 && flipped <= 52, "{flipped}");
+                total += flipped;
+                input[bit/8] ^= 1 << (bit%8);
+            }
+            let avg = total as f64 / (input.len()*8) as f64;
+            assert!((avg - 32.0).abs() < 2.0, "{avg}");
+        };
+        check(b"a");
+        check(b"abcdefg");
+        check(b"src/hash/hash_map.rs");
+        check(&[0; 100]);
+
+        // same for integers.
+        let mut total = 0;
+        for bit in 0..64 {
+            let flipped = (wyhash64(&(1u64 << bit)) ^ wyhash64(&0u64)).count_ones();
+            total += flipped;
+        }
+        assert!((total as f64 / 64.0 - 32.0).abs() < 3.0);
+    }
+
+    #[test]
+    #[ignore]
+    fn wyhash_bench() {
+        use std::time::Instant;
+        use core::hint::black_box;
+        use crate::hash::fxhash::fxhash32;
+
+        for len in [8, 16, 32, 64, 256, 4096] {
+            let bytes: std::vec::Vec<u8> = (0..len).map(|i| (i*31 + 1) as u8).collect();
+            let iters = (1 << 26) / len;
+
+            let t0 = Instant::now();
+            for _ in 0..iters {
+                black_box(fxhash32(black_box(&bytes[..])));
+            }
+            let fx = t0.elapsed();
+
+            let t0 = Instant::now();
+            for _ in 0..iters {
+                black_box(wyhash32(black_box(&bytes[..])));
+            }
+            let wy = t0.elapsed();
+
+            std::println!("len {len:5}: fxhash32 {:8.2?}  wyhash32 {:8.2?}  ({:.2}x)",
+                fx, wy, fx.as_secs_f64() / wy.as_secs_f64());
+        }
+    }
+}
@@ -1,16 +1,21 @@
 use crate::reader::Reader;
+use crate::writer::Buffer;
 
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Leb128Error {
     Overflow,
     EOI,
+    /// the output didn't have enough space.
+    OutOfSpace,
 }
 
 type Result<T> = core::result::Result<T, Leb128Error>;
 
 
+pub const MAX_SIZE_32: usize = 5;
 pub const MAX_SIZE_64: usize = 10;
+pub const MAX_SIZE_128: usize = 19;
 
 
 const CONT: u8 = 0x80;
@@ -79,3 +84,413 @@ pub fn decode_u32(reader: &mut Reader<u8>) -> Result<u32> {
     .try_into().ok().ok_or(Leb128Error::Overflow)
 }
 
+
+
+pub fn decode_i128(reader: &mut Reader<u8>) -> Result<i128> {
+    let mut result = 0;
+    let mut shift  = 0;
+    while let Some(byte) = reader.next() {
+        // overflow.
+        if shift == 126 && !(byte == 0x00 || byte == 0x01 || byte == 0x7e || byte == 0x7f) {
+            if !reader.consume_while(|byte| byte & CONT != 0) {
+                return Err(Leb128Error::EOI);
+            }
+            return Err(Leb128Error::Overflow);
+        }
+
+        result |= ((byte & MASK) as i128) << shift;
+        shift  += 7;
+
+        if byte & CONT == 0 {
+            // sign extend.
+            if shift < 128 && (byte & SIGN) == SIGN {
+                result |= !0 << shift;
+            }
+            return Ok(result);
+        }
+    }
+
+    return Err(Leb128Error::EOI);
+}
+
+pub fn decode_u128(reader: &mut Reader<u8>) -> Result<u128> {
+    let mut result = 0;
+    let mut shift  = 0;
+    while let Some(byte) = reader.next() {
+        // overflow.
+        if shift == 126 && byte > 0x03 {
+            if !reader.consume_while(|byte| byte & CONT != 0) {
+                return Err(Leb128Error::EOI);
+            }
+            return Err(Leb128Error::Overflow);
+        }
+
+        result |= ((byte & MASK) as u128) << shift;
+        shift  += 7;
+
+        if byte & CONT == 0 {
+            return Ok(result);
+        }
+    }
+
+    return Err(Leb128Error::EOI);
+}
+
+
+
+#[inline]
+fn write_all<O: Buffer + ?Sized>(out: &mut O, bytes: &[u8]) -> Result<usize> {
+    if !out.write(bytes) {
+        return Err(Leb128Error::OutOfSpace);
+    }
+    return Ok(bytes.len());
+}
+
+
+/// the number of bytes, `encode_u64` writes for `value`.
+#[inline]
+pub fn size_u64(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros() as usize;
+    return bits.max(1).div_ceil(7);
+}
+
+/// the number of bytes, `encode_i64` writes for `value`.
+#[inline]
+pub fn size_i64(value: i64) -> usize {
+    // the sign bit also needs to be encoded.
+    let bits = 64 - (value ^ (value >> 63)).leading_zeros() as usize + 1;
+    return bits.div_ceil(7);
+}
+
+/// the number of bytes, `encode_u128` writes for `value`.
+#[inline]
+pub fn size_u128(value: u128) -> usize {
+    let bits = 128 - value.leading_zeros() as usize;
+    return bits.max(1).div_ceil(7);
+}
+
+/// the number of bytes, `encode_i128` writes for `value`.
+#[inline]
+pub fn size_i128(value: i128) -> usize {
+    let bits = 128 - (value ^ (value >> 127)).leading_zeros() as usize + 1;
+    return bits.div_ceil(7);
+}
+
+
+macro_rules! encode_unsigned {
+    ($name:ident, $padded:ident, $patch:ident, $ty:ty, $max_size:expr) => {
+        /// encodes `value` and returns the number of bytes written.
+        ///
+        /// - `out` is any `writer::Buffer`: a `Vec<u8>`, a `SliceVec<u8>` or a `Writer`.
+        /// - writes nothing, if `out` doesn't have enough space.
+        pub fn $name<O: Buffer + ?Sized>(out: &mut O, value: $ty) -> Result<usize> {
+            let mut buffer = [0; $max_size];
+            let mut value = value;
+            let mut len = 0;
+            loop {
+                let byte = (value as u8) & MASK;
+                value >>= 7;
+
+                if value == 0 {
+                    buffer[len] = byte;
+                    len += 1;
+                    break;
+                }
+
+                buffer[len] = byte | CONT;
+                len += 1;
+            }
+            return write_all(out, &buffer[..len]);
+        }
+
+        /// encodes `value` using exactly `width` bytes.
+        ///
+        /// - useful for sizes, that are patched later. see `patch_*`.
+        /// - `width` must be in `1..=MAX_SIZE`.
+        /// - returns `Overflow`, if `value` doesn't fit into `width` bytes.
+        pub fn $padded<O: Buffer + ?Sized>(out: &mut O, value: $ty, width: usize) -> Result<usize> {
+            let mut buffer = [0; $max_size];
+            $patch(&mut buffer[..width], value)?;
+            return write_all(out, &buffer[..width]);
+        }
+
+        /// encodes `value` into `bytes` using exactly `bytes.len()` bytes.
+        ///
+        /// - `bytes.len()` must be in `1..=MAX_SIZE`.
+        /// - returns `Overflow`, if `value` doesn't fit.
+        pub fn $patch(bytes: &mut [u8], value: $ty) -> Result<()> {
+            let width = bytes.len();
+            assert!(width > 0 && width <= $max_size);
+
+            let mut value = value;
+            for i in 0..width {
+                let byte = (value as u8) & MASK;
+                value >>= 7;
+                bytes[i] = if i + 1 < width { byte | CONT } else { byte };
+            }
+
+            if value != 0 {
+                return Err(Leb128Error::Overflow);
+            }
+            return Ok(());
+        }
+    };
+}
+
+macro_rules! encode_signed {
+    ($name:ident, $padded:ident, $patch:ident, $ty:ty, $max_size:expr) => {
+        /// encodes `value` and returns the number of bytes written.
+        ///
+        /// - `out` is any `writer::Buffer`: a `Vec<u8>`, a `SliceVec<u8>` or a `Writer`.
+        /// - writes nothing, if `out` doesn't have enough space.
+        pub fn $name<O: Buffer + ?Sized>(out: &mut O, value: $ty) -> Result<usize> {
+            let mut buffer = [0; $max_size];
+            let mut value = value;
+            let mut len = 0;
+            loop {
+                let byte = (value as u8) & MASK;
+                value >>= 7;
+
+                // the remaining bits are the sign extension of `byte`.
+                let done =
+                       (value ==  0 && byte & SIGN == 0)
+                    || (value == -1 && byte & SIGN != 0);
+
+                if done {
+                    buffer[len] = byte;
+                    len += 1;
+                    break;
+                }
+
+                buffer[len] = byte | CONT;
+                len += 1;
+            }
+            return write_all(out, &buffer[..len]);
+        }
+
+        /// encodes `value` using exactly `width` bytes.
+        ///
+        /// - useful for values, that are patched later. see `patch_*`.
+        /// - `width` must be in `1..=MAX_SIZE`.
+        /// - returns `Overflow`, if `value` doesn't fit into `width` bytes.
+        pub fn $padded<O: Buffer + ?Sized>(out: &mut O, value: $ty, width: usize) -> Result<usize> {
+            let mut buffer = [0; $max_size];
+            $patch(&mut buffer[..width], value)?;
+            return write_all(out, &buffer[..width]);
+        }
+
+        /// encodes `value` into `bytes` using exactly `bytes.len()` bytes.
+        ///
+        /// - `bytes.len()` must be in `1..=MAX_SIZE`.
+        /// - returns `Overflow`, if `value` doesn't fit.
+        pub fn $patch(bytes: &mut [u8], value: $ty) -> Result<()> {
+            let width = bytes.len();
+            assert!(width > 0 && width <= $max_size);
+
+            let mut value = value;
+            let mut last = 0;
+            for i in 0..width {
+                let byte = (value as u8) & MASK;
+                // arithmetic shift, saturates at `0` or `-1`.
+                value >>= 7;
+                bytes[i] = if i + 1 < width { byte | CONT } else { byte };
+                last = byte;
+            }
+
+            let sign_ok =
+                   (value ==  0 && last & SIGN == 0)
+                || (value == -1 && last & SIGN != 0);
+            if !sign_ok {
+                return Err(Leb128Error::Overflow);
+            }
+            return Ok(());
+        }
+    };
+}
+
+encode_unsigned!(encode_u64,  encode_u64_padded,  patch_u64,  u64,  MAX_SIZE_64);
+encode_unsigned!(encode_u128, encode_u128_padded, patch_u128, u128, MAX_SIZE_128);
+encode_signed!(encode_i64,  encode_i64_padded,  patch_i64,  i64,  MAX_SIZE_64);
+encode_signed!(encode_i128, encode_i128_padded, patch_i128, i128, MAX_SIZE_128);
+
+#[inline]
+pub fn encode_u32<O: Buffer + ?Sized>(out: &mut O, value: u32) -> Result<usize> {
+    encode_u64(out, value as u64)
+}
+
+#[inline]
+pub fn encode_i32<O: Buffer + ?Sized>(out: &mut O, value: i32) -> Result<usize> {
+    encode_i64(out, value as i64)
+}
+
+/// encodes `value` using `MAX_SIZE_32` bytes.
+///
+/// - the result can be patched with `patch_u32`.
+#[inline]
+pub fn encode_u32_padded<O: Buffer + ?Sized>(out: &mut O, value: u32) -> Result<usize> {
+    encode_u64_padded(out, value as u64, MAX_SIZE_32)
+}
+
+/// patches a value written by `encode_u32_padded`.
+///
+/// - `bytes.len()` must be in `1..=MAX_SIZE_32`.
+#[inline]
+pub fn patch_u32(bytes: &mut [u8], value: u32) -> Result<()> {
+    assert!(bytes.len() <= MAX_SIZE_32);
+    patch_u64(bytes, value as u64)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec::Vec;
+    use crate::mem::MaybeUninit;
+    use crate::slice_vec::SliceVec;
+    use crate::writer::Writer;
+
+    fn enc_u64(value: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        let n = encode_u64(&mut out, value).unwrap();
+        assert_eq!(n, out.len());
+        assert_eq!(n, size_u64(value));
+        return out;
+    }
+
+    fn enc_i64(value: i64) -> Vec<u8> {
+        let mut out = Vec::new();
+        let n = encode_i64(&mut out, value).unwrap();
+        assert_eq!(n, out.len());
+        assert_eq!(n, size_i64(value));
+        return out;
+    }
+
+    #[test]
+    fn leb128_known() {
+        assert_eq!(**enc_u64(0), [0x00]);
+        assert_eq!(**enc_u64(127), [0x7f]);
+        assert_eq!(**enc_u64(128), [0x80, 0x01]);
+        assert_eq!(**enc_u64(624485), [0xe5, 0x8e, 0x26]);
+        assert_eq!(enc_u64(u64::MAX).len(), MAX_SIZE_64);
+
+        assert_eq!(**enc_i64(0), [0x00]);
+        assert_eq!(**enc_i64(-1), [0x7f]);
+        assert_eq!(**enc_i64(63), [0x3f]);
+        assert_eq!(**enc_i64(64), [0xc0, 0x00]);
+        assert_eq!(**enc_i64(-64), [0x40]);
+        assert_eq!(**enc_i64(-65), [0xbf, 0x7f]);
+        assert_eq!(**enc_i64(-123456), [0xc0, 0xbb, 0x78]);
+        assert_eq!(enc_i64(i64::MIN).len(), MAX_SIZE_64);
+    }
+
+    #[test]
+    fn leb128_round_trip() {
+        let mut values = std::vec::Vec::new();
+        for shift in 0..64 {
+            let v = 1u64 << shift;
+            values.extend([v - 1, v, v + 1, !v, v.wrapping_neg()]);
+        }
+
+        for &v in &values {
+            let bytes = enc_u64(v);
+            assert_eq!(decode_u64(&mut Reader::new(&bytes)).unwrap(), v);
+
+            let bytes = enc_i64(v as i64);
+            assert_eq!(decode_i64(&mut Reader::new(&bytes)).unwrap(), v as i64);
+
+            let mut out = Vec::new();
+            assert_eq!(encode_u128(&mut out, (v as u128) << 64 | v as u128).unwrap(), out.len());
+            assert_eq!(out.len(), size_u128((v as u128) << 64 | v as u128));
+            assert_eq!(decode_u128(&mut Reader::new(&out)).unwrap(), (v as u128) << 64 | v as u128);
+
+            let w = ((v as i128) << 64 | v as i128) >> (v % 64);
+            let mut out = Vec::new();
+            encode_i128(&mut out, w).unwrap();
+            assert_eq!(out.len(), size_i128(w));
+            assert_eq!(decode_i128(&mut Reader::new(&out)).unwrap(), w);
+        }
+
+        for v in [u128::MAX, 0, 1] {
+            let mut out = Vec::new();
+            encode_u128(&mut out, v).unwrap();
+            assert!(out.len() <= MAX_SIZE_128);
+            assert_eq!(decode_u128(&mut Reader::new(&out)).unwrap(), v);
+        }
+        for v in [i128::MIN, i128::MAX, -1, 0] {
+            let mut out = Vec::new();
+            encode_i128(&mut out, v).unwrap();
+            assert!(out.len() <= MAX_SIZE_128);
+            assert_eq!(decode_i128(&mut Reader::new(&out)).unwrap(), v);
+        }
+
+        let mut out = Vec::new();
+        encode_u32(&mut out, u32::MAX).unwrap();
+        encode_i32(&mut out, i32::MIN).unwrap();
+        let mut reader = Reader::new(&out);
+        assert_eq!(decode_u32(&mut reader).unwrap(), u32::MAX);
+        assert_eq!(decode_i32(&mut reader).unwrap(), i32::MIN);
+    }
+
+    #[test]
+    fn leb128_padded() {
+        let mut out = Vec::new();
+        assert_eq!(encode_u32_padded(&mut out, 0), Ok(5));
+        assert_eq!(**out, [0x80, 0x80, 0x80, 0x80, 0x00]);
+        assert_eq!(decode_u32(&mut Reader::new(&out)), Ok(0));
+
+        // patch a section size.
+        patch_u32(&mut out[0..5], 624485).unwrap();
+        assert_eq!(**out, [0xe5, 0x8e, 0xa6, 0x80, 0x00]);
+        assert_eq!(decode_u32(&mut Reader::new(&out)), Ok(624485));
+
+        let mut out = Vec::new();
+        assert_eq!(encode_i64_padded(&mut out, -2, 3), Ok(3));
+        assert_eq!(**out, [0xfe, 0xff, 0x7f]);
+        assert_eq!(decode_i64(&mut Reader::new(&out)), Ok(-2));
+
+        let mut out = Vec::new();
+        assert_eq!(encode_u64_padded(&mut out, 128, 1), Err(Leb128Error::Overflow));
+        assert_eq!(encode_i64_padded(&mut out, 64, 1), Err(Leb128Error::Overflow));
+        assert_eq!(encode_i64_padded(&mut out, -65, 1), Err(Leb128Error::Overflow));
+        assert_eq!(out.len(), 0);
+
+        assert_eq!(encode_u64_padded(&mut out, u64::MAX, MAX_SIZE_64), Ok(MAX_SIZE_64));
+        assert_eq!(encode_i64_padded(&mut out, i64::MIN, MAX_SIZE_64), Ok(MAX_SIZE_64));
+        assert_eq!(encode_u128_padded(&mut out, u128::MAX, MAX_SIZE_128), Ok(MAX_SIZE_128));
+        let mut reader = Reader::new(&out);
+        assert_eq!(decode_u64(&mut reader), Ok(u64::MAX));
+        assert_eq!(decode_i64(&mut reader), Ok(i64::MIN));
+        assert_eq!(decode_u128(&mut reader), Ok(u128::MAX));
+    }
+
+    #[test]
+    fn leb128_slice_vec() {
+        let mut buffer = [MaybeUninit::uninit(); 4];
+        let mut out = SliceVec::new(&mut buffer);
+        assert_eq!(encode_u64(&mut out, 624485), Ok(3));
+        assert_eq!(encode_u64(&mut out, 128), Err(Leb128Error::OutOfSpace));
+        assert_eq!(encode_u64(&mut out, 1), Ok(1));
+        assert_eq!(out.as_slice(), &[0xe5, 0x8e, 0x26, 0x01]);
+    }
+
+    #[test]
+    fn leb128_writer_patch() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut w = Writer::new(&mut buffer);
+        w.write_u8(1).unwrap();
+
+        // wasm style section size.
+        let size = w.reserve_patch::<MAX_SIZE_32>().unwrap();
+        encode_u32(&mut w, 300).unwrap();
+        w.write_bytes(b"abc").unwrap();
+        let n = (w.offset() - size.end()) as u32;
+        patch_u32(w.patch_bytes(size), n).unwrap();
+        drop(w);
+
+        let mut r = Reader::new(&buffer);
+        assert_eq!(r.next(), Some(1));
+        assert_eq!(decode_u32(&mut r), Ok(5));
+        assert_eq!(decode_u32(&mut r), Ok(300));
+        assert_eq!(r.as_slice(), b"abc");
+    }
+}
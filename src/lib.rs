@@ -25,6 +25,7 @@ pub mod fmt;
 
 pub mod ext;
 pub mod reader;
+pub mod writer;
 pub mod static_vec;
 pub mod slice_vec;
 pub mod byte_mask;
@@ -33,7 +34,9 @@ pub mod alloc;
 pub mod asan;
 pub mod arena;
 pub mod boxed;
+pub mod rc;
 pub mod vec;
+pub mod vec_deque;
 pub mod hash;
 pub mod string;
 pub mod lru;
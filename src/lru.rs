@@ -185,6 +185,7 @@ impl<K, V, A: Alloc, H: HashFn<K, u32>> Lru<K, V, A, H> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::hash::hash_map::Group;
 
     #[test]
     fn lru_basic() {
@@ -332,12 +333,21 @@ mod test {
     #[test]
     fn lru_rehash() {
         let mut num_rehashes = 0;
+        let mut num_tombs = 0;
         let mut prev_resident = 0;
 
-        let mut lru: Lru<u32, (), GlobalAlloc, FxHashFn> = Lru::with_hash_and_cap_in(GlobalAlloc, FxHashFn, 23);
+        // removes only leave tombstones in groups without fresh slots,
+        // so the map needs to be close to full.
+        // 4 groups, 5 empty slots short of the map's cap.
+        // (23 for 8 wide groups.)
+        let cap = 4*Group::WIDTH*7/8 - 5;
+        let mut lru: Lru<u32, (), GlobalAlloc, FxHashFn> = Lru::with_hash_and_cap_in(GlobalAlloc, FxHashFn, cap);
         for i in 0..1000 {
             lru.get_or_insert(i, || ());
 
+            // tombstones are resident, but not used.
+            num_tombs = num_tombs.max(lru.map.resident() - lru.map.len());
+
             // we do +1 here to make sure it's really a rehash.
             // when we evict, we may get one empty slot back,
             // but then on insert, we may reuse a tombstone.
@@ -354,6 +364,7 @@ mod test {
             prev_resident = lru.map.resident();
         }
 
+        assert!(num_tombs > 0);
         assert!(num_rehashes > 0);
     }
 }
@@ -5,7 +5,8 @@ pub use core::{
         MaybeUninit, ManuallyDrop,
         transmute, transmute_copy,
         take, replace, swap, forget,
-        size_of, size_of_val, align_of, offset_of,
+        size_of, size_of_val, align_of, align_of_val, offset_of,
+        needs_drop,
     },
     ptr::{
         NonNull,
@@ -5,6 +5,8 @@ pub use crate::ext::{FromIn, MapIt, CopyIt, InsertNew, OkVal};
 pub use crate::alloc::{Alloc, GlobalAlloc};
 pub use crate::arena::Arena;
 pub use crate::boxed::Box;
+pub use crate::rc::Rc;
+pub use crate::sync::arc::Arc;
 pub use crate::vec::{Vec, ZVec, KVec};
 pub use crate::string::String;
 pub use crate::hash::HashMap;
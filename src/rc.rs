@@ -0,0 +1,351 @@
+use crate::mem::{NonNull, Cell, PhantomData, ManuallyDrop};
+use crate::alloc::{Alloc, GlobalAlloc, Layout};
+
+
+/// a single threaded reference counted pointer.
+///
+/// - the allocator is stored in the allocation,
+///   so `Rc` is a single pointer, and `A` need not be `Clone`.
+/// - see `unsize_rc!` for unsizing.
+pub struct Rc<T: ?Sized, A: Alloc = GlobalAlloc> {
+    inner: NonNull<RcInner<T, A>>,
+    phantom: PhantomData<RcInner<T, A>>,
+}
+
+/// a non-owning reference to an `Rc` allocation.
+///
+/// - see `Rc::downgrade`.
+pub struct Weak<T: ?Sized, A: Alloc = GlobalAlloc> {
+    inner: NonNull<RcInner<T, A>>,
+}
+
+struct RcHeader<A: Alloc> {
+    strong: Cell<usize>,
+
+    /// - the strong refs collectively hold one weak ref.
+    weak: Cell<usize>,
+
+    alloc: A,
+}
+
+#[repr(C)]
+struct RcInner<T: ?Sized, A: Alloc> {
+    header: RcHeader<A>,
+    value: T,
+}
+
+
+impl<T> Rc<T, GlobalAlloc> {
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Rc::new_in(GlobalAlloc, value)
+    }
+}
+
+impl<T, A: Alloc> Rc<T, A> {
+    pub fn new_in(alloc: A, value: T) -> Self {
+        let inner = crate::alloc::alloc_ptr::<RcInner<T, A>>(&alloc).unwrap();
+        unsafe {
+            inner.as_ptr().write(RcInner {
+                header: RcHeader {
+                    strong: Cell::new(1),
+                    weak: Cell::new(1),
+                    alloc,
+                },
+                value,
+            });
+        }
+        return Rc { inner, phantom: PhantomData };
+    }
+
+    /// returns the value, if `this` is the only strong ref.
+    ///
+    /// - weak refs can no longer be upgraded.
+    pub fn into_inner(this: Self) -> Option<T> {
+        let this = ManuallyDrop::new(this);
+
+        let header = this.header();
+        if header.strong.get() != 1 {
+            // drop `this`.
+            drop(ManuallyDrop::into_inner(this));
+            return None;
+        }
+        header.strong.set(0);
+
+        unsafe {
+            let value = core::ptr::addr_of!((*this.inner.as_ptr()).value).read();
+            drop_weak(this.inner);
+            return Some(value);
+        }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Rc<T, A> {
+    #[inline(always)]
+    fn header(&self) -> &RcHeader<A> {
+        unsafe { &self.inner.as_ref().header }
+    }
+
+    #[inline(always)]
+    pub fn strong_count(this: &Self) -> usize { this.header().strong.get() }
+
+    #[inline(always)]
+    pub fn weak_count(this: &Self) -> usize { this.header().weak.get() - 1 }
+
+    /// the allocator, the value was allocated in.
+    #[inline(always)]
+    pub fn allocator(this: &Self) -> &A { &this.header().alloc }
+
+    #[inline(always)]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.inner.as_ptr() as *const u8 == other.inner.as_ptr() as *const u8
+    }
+
+    #[inline(always)]
+    pub fn as_ptr(this: &Self) -> NonNull<T> {
+        unsafe { NonNull::new_unchecked(core::ptr::addr_of_mut!((*this.inner.as_ptr()).value)) }
+    }
+
+    /// returns a mutable reference to the value,
+    /// if there are no other strong or weak refs.
+    #[inline]
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        let header = this.header();
+        if header.strong.get() == 1 && header.weak.get() == 1 {
+            return Some(unsafe { &mut this.inner.as_mut().value });
+        }
+        return None;
+    }
+
+    pub fn downgrade(this: &Self) -> Weak<T, A> {
+        let weak = &this.header().weak;
+        weak.set(weak.get().checked_add(1).unwrap());
+        return Weak { inner: this.inner };
+    }
+
+    /// consumes the `Rc` without decrementing the strong count.
+    ///
+    /// - see `from_raw`.
+    #[inline]
+    pub fn into_raw(this: Self) -> NonNull<T> {
+        let this = ManuallyDrop::new(this);
+        return Rc::as_ptr(&this);
+    }
+
+    /// # safety:
+    /// - `ptr` must have been returned by `Rc::<U, A>::into_raw`,
+    ///   where `U` is `T` or unsizes to `T`.
+    /// - each call to `into_raw` can be matched by at most one `from_raw`.
+    #[inline]
+    pub unsafe fn from_raw(ptr: NonNull<T>) -> Self {
+        unsafe {
+            let offset = value_offset::<A>(crate::mem::align_of_val(ptr.as_ref()));
+            let inner = ptr.as_ptr().byte_sub(offset) as *mut RcInner<T, A>;
+            return Rc { inner: NonNull::new_unchecked(inner), phantom: PhantomData };
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn __into_unsize(this: Self) -> (NonNull<T>, PhantomData<A>) {
+        (Rc::into_raw(this), PhantomData)
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub unsafe fn __from_unsize(ptr: NonNull<T>, _: PhantomData<A>) -> Self {
+        unsafe { Rc::from_raw(ptr) }
+    }
+}
+
+/// the offset of `RcInner::value`.
+#[inline(always)]
+fn value_offset<A: Alloc>(align: usize) -> usize {
+    crate::num::ceil_to_multiple_pow2(crate::mem::size_of::<RcHeader<A>>(), align)
+}
+
+/// drops a weak ref.
+///
+/// # safety:
+/// - the caller must own a weak ref to `inner`.
+/// - if this is the last weak ref, the value must have been dropped.
+unsafe fn drop_weak<T: ?Sized, A: Alloc>(inner: NonNull<RcInner<T, A>>) {
+    unsafe {
+        let weak = &inner.as_ref().header.weak;
+        weak.set(weak.get() - 1);
+        if weak.get() == 0 {
+            let layout = Layout::for_value(inner.as_ref());
+            let alloc = core::ptr::addr_of!((*inner.as_ptr()).header.alloc).read();
+            alloc.free(inner.cast(), layout);
+        }
+    }
+}
+
+
+impl<T: ?Sized, A: Alloc> Clone for Rc<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let strong = &self.header().strong;
+        strong.set(strong.get().checked_add(1).unwrap());
+        return Rc { inner: self.inner, phantom: PhantomData };
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Drop for Rc<T, A> {
+    #[inline]
+    fn drop(&mut self) {
+        let strong = &self.header().strong;
+        strong.set(strong.get() - 1);
+        if strong.get() == 0 {
+            unsafe {
+                crate::mem::drop_in_place(&mut self.inner.as_mut().value);
+                drop_weak(self.inner);
+            }
+        }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> core::ops::Deref for Rc<T, A> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &self.inner.as_ref().value }
+    }
+}
+
+impl<T: ?Sized + core::fmt::Debug, A: Alloc> core::fmt::Debug for Rc<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        (&**self).fmt(f)
+    }
+}
+
+
+impl<T: ?Sized, A: Alloc> Weak<T, A> {
+    /// returns a strong ref, if the value hasn't been dropped.
+    pub fn upgrade(&self) -> Option<Rc<T, A>> {
+        let strong = unsafe { &self.inner.as_ref().header.strong };
+        if strong.get() == 0 {
+            return None;
+        }
+        strong.set(strong.get().checked_add(1).unwrap());
+        return Some(Rc { inner: self.inner, phantom: PhantomData });
+    }
+
+    #[inline(always)]
+    pub fn strong_count(&self) -> usize {
+        unsafe { self.inner.as_ref().header.strong.get() }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Clone for Weak<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let weak = unsafe { &self.inner.as_ref().header.weak };
+        weak.set(weak.get().checked_add(1).unwrap());
+        return Weak { inner: self.inner };
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Drop for Weak<T, A> {
+    #[inline]
+    fn drop(&mut self) {
+        // we own a weak ref.
+        // if it's the last one, the strong refs are gone,
+        // so the value has been dropped.
+        unsafe { drop_weak(self.inner) }
+    }
+}
+
+
+/// unsizes an `Rc`.
+///
+/// - usage: `let rc: Rc<dyn Trait> = unsize_rc!(Rc::new(value));`
+#[macro_export]
+macro_rules! unsize_rc {
+    ($rc:expr) => {{
+        let (ptr, alloc) = $crate::rc::Rc::__into_unsize($rc);
+        // `ptr` is unsized by coercion.
+        unsafe { $crate::rc::Rc::__from_unsize(ptr, alloc) }
+    }};
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+
+    #[test]
+    fn rc_basic() {
+        let a = Rc::new(42);
+        assert_eq!(*a, 42);
+        assert_eq!(Rc::strong_count(&a), 1);
+        assert_eq!(Rc::weak_count(&a), 0);
+
+        let mut b = a.clone();
+        assert!(Rc::ptr_eq(&a, &b));
+        assert_eq!(Rc::strong_count(&a), 2);
+        assert!(Rc::get_mut(&mut b).is_none());
+
+        drop(a);
+        *Rc::get_mut(&mut b).unwrap() += 1;
+        assert_eq!(*b, 43);
+
+        let w = Rc::downgrade(&b);
+        assert_eq!(Rc::weak_count(&b), 1);
+        assert!(Rc::get_mut(&mut b).is_none());
+
+        let c = w.upgrade().unwrap();
+        assert_eq!(w.strong_count(), 2);
+        assert!(Rc::into_inner(c).is_none());
+
+        assert_eq!(Rc::into_inner(b), Some(43));
+        assert_eq!(w.strong_count(), 0);
+        assert!(w.upgrade().is_none());
+    }
+
+    #[test]
+    fn rc_drop() {
+        let value = Rc::new(());
+        let a = Rc::new(value.clone());
+        let w = Rc::downgrade(&a);
+        assert_eq!(Rc::strong_count(&value), 2);
+
+        drop(a);
+        assert_eq!(Rc::strong_count(&value), 1);
+        assert!(w.upgrade().is_none());
+        drop(w);
+    }
+
+    #[test]
+    fn rc_raw_and_unsize() {
+        let a = Rc::new(std::string::String::from("hi"));
+        let raw = Rc::into_raw(a);
+        let a = unsafe { Rc::from_raw(raw) };
+        assert_eq!(*a, "hi");
+
+        let b: Rc<dyn core::fmt::Debug> = crate::unsize_rc!(a.clone());
+        assert_eq!(std::format!("{:?}", b), "\"hi\"");
+        assert_eq!(Rc::strong_count(&a), 2);
+
+        let raw = Rc::into_raw(b);
+        let b = unsafe { Rc::<dyn core::fmt::Debug>::from_raw(raw) };
+        drop(b);
+        assert_eq!(Rc::strong_count(&a), 1);
+
+        let s: Rc<[u64]> = crate::unsize_rc!(Rc::new([1u64, 2, 3]));
+        assert_eq!(&*s, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn rc_in_arena() {
+        let arena = Arena::new();
+        let a = Rc::new_in(&arena, 1u8);
+        let b = a.clone();
+        assert!(core::ptr::eq(*Rc::allocator(&b), &arena));
+        let c: Rc<dyn core::fmt::Debug, &Arena> = crate::unsize_rc!(b);
+        drop(a);
+        drop(c);
+    }
+}
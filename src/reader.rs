@@ -1,3 +1,39 @@
+use core::ops::Range;
+
+
+/// the input ended before a value could be read.
+///
+/// - `offset` is where the value would have started.
+/// - `needed` and `available` are in elements (bytes, for `Reader<u8>`).
+/// - the reader's offset is not changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadError {
+    pub offset:    usize,
+    pub needed:    usize,
+    pub available: usize,
+}
+
+
+/// a saved reader position. see `Reader::checkpoint`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    offset: usize,
+}
+
+impl Checkpoint {
+    #[inline(always)]
+    pub fn offset(&self) -> usize { self.offset }
+}
+
+
+/// a 1-based line and (byte) column. see `Reader::line_col`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineCol {
+    pub line:   usize,
+    pub column: usize,
+}
+
+
 /// a slice reader to aid with parsing.
 ///
 /// - `deref`s into a slice with the remaining values.
@@ -71,6 +107,55 @@ impl<'a, T> Reader<'a, T> {
         self.remaining = &self.original_slice()[new_offset..];
     }
 
+    /// saves the current position.
+    ///
+    /// - only valid for this reader (and its clones).
+    #[inline(always)]
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint { offset: self.offset() }
+    }
+
+    /// restores a position saved by `checkpoint`.
+    ///
+    /// # panics
+    /// - if the checkpoint is out of bounds.
+    #[inline]
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.set_offset(checkpoint.offset);
+    }
+
+    /// the range from `checkpoint` to the current offset.
+    ///
+    /// # panics
+    /// - if the checkpoint is after the current offset.
+    #[inline]
+    pub fn span_from(&self, checkpoint: Checkpoint) -> Range<usize> {
+        let offset = self.offset();
+        assert!(checkpoint.offset <= offset);
+        return checkpoint.offset..offset;
+    }
+
+    /// the values consumed since `checkpoint`.
+    ///
+    /// - same as `&original_slice[span_from(checkpoint)]`.
+    #[inline]
+    pub fn slice_from(&self, checkpoint: Checkpoint) -> &'a [T] {
+        &self.original_slice()[self.span_from(checkpoint)]
+    }
+
+    /// runs `f`, and restores the offset, if `f` returns `None`.
+    ///
+    /// - useful for speculative parsing.
+    #[inline]
+    pub fn with_backtrack<R, F: FnOnce(&mut Self) -> Option<R>>(&mut self, f: F) -> Option<R> {
+        let checkpoint = self.checkpoint();
+        let result = f(self);
+        if result.is_none() {
+            self.rewind(checkpoint);
+        }
+        return result;
+    }
+
     /// number of consumed values.
     ///
     /// - same as `offset`.
@@ -151,6 +236,21 @@ impl<'a, T> Reader<'a, T> {
         return None;
     }
 
+    /// next `n` elements and advance offset.
+    ///
+    /// - like `next_n`, but returns where and by how much the input was short.
+    #[inline]
+    pub fn try_next_n(&mut self, n: usize) -> Result<&'a [T], ReadError> {
+        if let Some(result) = self.next_n(n) {
+            return Ok(result);
+        }
+        return Err(ReadError {
+            offset:    self.offset(),
+            needed:    n,
+            available: self.remaining(),
+        });
+    }
+
     /// reference to next element and advance offset, if predicate true.
     #[inline]
     pub fn next_ref_if<F: FnOnce(&T) -> bool>(&mut self, f: F) -> Option<&'a T> {
@@ -252,6 +352,12 @@ impl<'a, T: Copy> Reader<'a, T> {
         Some(slice.try_into().expect("unreachable"))
     }
 
+    #[inline]
+    pub fn try_next_array<const N: usize>(&mut self) -> Result<[T; N], ReadError> {
+        let slice = self.try_next_n(N)?;
+        Ok(slice.try_into().expect("unreachable"))
+    }
+
 
     #[inline]
     pub fn expect(&mut self, value: T) -> Result<(), ()>  where T: PartialEq {
@@ -281,70 +387,294 @@ impl<'a, T: Copy> Reader<'a, T> {
     }
 }
 
+macro_rules! reader_getters {
+    ($($ty:ty: $le:ident, $be:ident, $try_le:ident, $try_be:ident;)*) => {
+        impl<'a> Reader<'a, u8> {
+            $(
+                #[inline]
+                pub fn $le(&mut self) -> Option<$ty> {
+                    Some(<$ty>::from_le_bytes(self.next_array()?))
+                }
+
+                #[inline]
+                pub fn $be(&mut self) -> Option<$ty> {
+                    Some(<$ty>::from_be_bytes(self.next_array()?))
+                }
+
+                #[inline]
+                pub fn $try_le(&mut self) -> Result<$ty, ReadError> {
+                    Ok(<$ty>::from_le_bytes(self.try_next_array()?))
+                }
+
+                #[inline]
+                pub fn $try_be(&mut self) -> Result<$ty, ReadError> {
+                    Ok(<$ty>::from_be_bytes(self.try_next_array()?))
+                }
+            )*
+        }
+    };
+}
+
+reader_getters! {
+    u8:   next_u8_le,   next_u8_be,   try_next_u8_le,   try_next_u8_be;
+    u16:  next_u16_le,  next_u16_be,  try_next_u16_le,  try_next_u16_be;
+    u32:  next_u32_le,  next_u32_be,  try_next_u32_le,  try_next_u32_be;
+    u64:  next_u64_le,  next_u64_be,  try_next_u64_le,  try_next_u64_be;
+    u128: next_u128_le, next_u128_be, try_next_u128_le, try_next_u128_be;
+    i8:   next_i8_le,   next_i8_be,   try_next_i8_le,   try_next_i8_be;
+    i16:  next_i16_le,  next_i16_be,  try_next_i16_le,  try_next_i16_be;
+    i32:  next_i32_le,  next_i32_be,  try_next_i32_le,  try_next_i32_be;
+    i64:  next_i64_le,  next_i64_be,  try_next_i64_le,  try_next_i64_be;
+    i128: next_i128_le, next_i128_be, try_next_i128_le, try_next_i128_be;
+    f32:  next_f32_le,  next_f32_be,  try_next_f32_le,  try_next_f32_be;
+    f64:  next_f64_le,  next_f64_be,  try_next_f64_le,  try_next_f64_be;
+}
+
 impl<'a> Reader<'a, u8> {
+    /// index of the next `byte` in the remaining input.
+    ///
+    /// - does not consume any input.
     #[inline]
-    pub fn next_u8_le(&mut self) -> Option<u8> {
-        self.next()
+    pub fn find_byte(&self, byte: u8) -> Option<usize> {
+        scan::find_byte(self.remaining, byte)
     }
 
+    /// index of the next byte in the remaining input, that is in `bytes`.
+    ///
+    /// - does not consume any input.
     #[inline]
-    pub fn next_u8_be(&mut self) -> Option<u8> {
-        self.next()
+    pub fn find_any(&self, bytes: &[u8]) -> Option<usize> {
+        scan::find_any(self.remaining, bytes)
     }
 
+    /// consume input, until the next byte is in `bytes`.
+    ///
+    /// - returns a bool `no_eoi`, that's `false`, if the end of input was reached
+    ///   before a byte in `bytes` was found. (same as `consume_while`)
     #[inline]
-    pub fn next_u16_le(&mut self) -> Option<u16> {
-        Some(u16::from_le_bytes(self.next_array()?))
+    pub fn consume_until_any(&mut self, bytes: &[u8]) -> bool {
+        match self.find_any(bytes) {
+            Some(i) => { self.consume(i); true }
+            None    => { self.consume(self.remaining()); false }
+        }
     }
 
+    /// consume ascii whitespace.
+    ///
+    /// - see `u8::is_ascii_whitespace`.
+    /// - returns a bool `no_eoi`. (same as `consume_while`)
     #[inline]
-    pub fn next_u16_be(&mut self) -> Option<u16> {
-        Some(u16::from_be_bytes(self.next_array()?))
+    pub fn consume_ascii_whitespace(&mut self) -> bool {
+        match scan::find_non_ascii_whitespace(self.remaining) {
+            Some(i) => { self.consume(i); true }
+            None    => { self.consume(self.remaining()); false }
+        }
     }
 
+    /// consume input, until the next byte is `\n`.
+    ///
+    /// - the newline is not consumed.
+    /// - returns a bool `no_eoi`, that's `false`, if there was no newline.
+    /// - useful for line comments.
     #[inline]
-    pub fn next_u32_le(&mut self) -> Option<u32> {
-        Some(u32::from_le_bytes(self.next_array()?))
+    pub fn skip_until_newline(&mut self) -> bool {
+        match self.find_byte(b'\n') {
+            Some(i) => { self.consume(i); true }
+            None    => { self.consume(self.remaining()); false }
+        }
     }
 
-    #[inline]
-    pub fn next_u32_be(&mut self) -> Option<u32> {
-        Some(u32::from_be_bytes(self.next_array()?))
+
+    /// the line and column of `offset` in the `original_slice`.
+    ///
+    /// - lines are separated by `\n`.
+    /// - columns count bytes, so a `\r` before a `\n` is a column.
+    /// - `O(offset)`, intended for error reporting.
+    ///
+    /// # panics
+    /// - if `offset > self.original_len()`.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let before = &self.original_slice()[..offset];
+
+        let mut line = 1;
+        let mut line_start = 0;
+        for (i, b) in before.iter().enumerate() {
+            if *b == b'\n' {
+                line += 1;
+                line_start = i + 1;
+            }
+        }
+
+        return LineCol { line, column: offset - line_start + 1 };
     }
 
     #[inline]
-    pub fn next_u64_le(&mut self) -> Option<u64> {
-        Some(u64::from_le_bytes(self.next_array()?))
+    pub unsafe fn next_ctype<T>(&mut self) -> Option<T> {
+        if self.len() < size_of::<T>() {
+            return None;
+        }
+
+        let result = unsafe { self.as_ptr().cast::<T>().read() };
+        self.consume(size_of::<T>());
+        return Some(result);
     }
+}
 
-    #[inline]
-    pub fn next_u64_be(&mut self) -> Option<u64> {
-        Some(u64::from_be_bytes(self.next_array()?))
+mod scan {
+    use crate::byte_mask::{ByteMask8, splat_8};
+
+    // scanning loops.
+    // - the `hit` functions return masks of the matching bytes.
+    //   only the first match needs to be exact.
+    // - processes 16 bytes per step with sse2, then 8 bytes
+    //   per step with swar, then single bytes.
+
+    #[inline(always)]
+    fn scan_swar<F8, F1>(bytes: &[u8], mut i: usize, hit_8: F8, hit_1: F1) -> Option<usize>
+    where F8: Fn(u64) -> ByteMask8, F1: Fn(u8) -> bool {
+        while i + 8 <= bytes.len() {
+            let word = u64::from_le_bytes(bytes[i..i+8].try_into().expect("unreachable"));
+            if let Some(j) = hit_8(word).next() {
+                return Some(i + j);
+            }
+            i += 8;
+        }
+
+        while i < bytes.len() {
+            if hit_1(bytes[i]) {
+                return Some(i);
+            }
+            i += 1;
+        }
+
+        return None;
     }
 
-    #[inline]
-    pub fn next_u128_le(&mut self) -> Option<u128> {
-        Some(u128::from_le_bytes(self.next_array()?))
+    crate::cfg_if! {
+        if #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))] {
+            use crate::arch::x86_64::*;
+
+            /// returns the index of the first hit and the index,
+            /// at which the remaining bytes should be scanned.
+            #[inline(always)]
+            fn scan_sse2<F16: Fn(__m128i) -> __m128i>(bytes: &[u8], hit_16: F16) -> (Option<usize>, usize) {
+                let mut i = 0;
+                while i + 16 <= bytes.len() {
+                    let chunk = unsafe { _mm_loadu_si128(bytes.as_ptr().add(i).cast()) };
+                    let mask = unsafe { _mm_movemask_epi8(hit_16(chunk)) } as u32;
+                    if mask != 0 {
+                        return (Some(i + mask.trailing_zeros() as usize), i);
+                    }
+                    i += 16;
+                }
+                return (None, i);
+            }
+        }
     }
 
-    #[inline]
-    pub fn next_u128_be(&mut self) -> Option<u128> {
-        Some(u128::from_be_bytes(self.next_array()?))
+    /// exact zero byte mask.
+    /// unlike `ByteMask8::find_zero_bytes`, this has no false positives.
+    #[inline(always)]
+    fn zero_bytes_exact(word: u64) -> u64 {
+        let non_zero = ((word & splat_8(0x7f)).wrapping_add(splat_8(0x7f))) | word;
+        return !non_zero & splat_8(0x80);
+    }
+
+    #[inline(always)]
+    fn equal_bytes_exact(word: u64, byte: u8) -> u64 {
+        zero_bytes_exact(word ^ splat_8(byte))
     }
 
 
     #[inline]
-    pub unsafe fn next_ctype<T>(&mut self) -> Option<T> {
-        if self.len() < size_of::<T>() {
-            return None;
+    pub fn find_byte(bytes: &[u8], byte: u8) -> Option<usize> {
+        #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+        let i = match scan_sse2(bytes, |chunk| unsafe {
+            _mm_cmpeq_epi8(chunk, _mm_set1_epi8(byte as i8))
+        }) {
+            (Some(hit), _) => return Some(hit),
+            (None, i) => i,
+        };
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+        let i = 0;
+
+        return scan_swar(bytes, i,
+            |word| ByteMask8::find_equal_bytes(word, byte),
+            |b| b == byte);
+    }
+
+    #[inline]
+    pub fn find_any(bytes: &[u8], set: &[u8]) -> Option<usize> {
+        match set.len() {
+            0 => return None,
+            1 => return find_byte(bytes, set[0]),
+            _ => (),
         }
 
-        let result = unsafe { self.as_ptr().cast::<T>().read() };
-        self.consume(size_of::<T>());
-        return Some(result);
+        #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+        let i = match scan_sse2(bytes, |chunk| unsafe {
+            let mut result = _mm_setzero_si128();
+            for b in set {
+                result = _mm_or_si128(result, _mm_cmpeq_epi8(chunk, _mm_set1_epi8(*b as i8)));
+            }
+            result
+        }) {
+            (Some(hit), _) => return Some(hit),
+            (None, i) => i,
+        };
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+        let i = 0;
+
+        return scan_swar(bytes, i,
+            |word| {
+                // the first match of each mask is exact,
+                // so the first match of the union is too.
+                let mut result = ByteMask8::NONE;
+                for b in set {
+                    result = result | ByteMask8::find_equal_bytes(word, *b);
+                }
+                result
+            },
+            |b| set.contains(&b));
+    }
+
+    #[inline]
+    pub fn find_non_ascii_whitespace(bytes: &[u8]) -> Option<usize> {
+        #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+        let i = match scan_sse2(bytes, |chunk| unsafe {
+            let ws =
+                _mm_or_si128(
+                    _mm_or_si128(
+                        _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b' ' as i8)),
+                        _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\t' as i8))),
+                    _mm_or_si128(
+                        _mm_or_si128(
+                            _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\n' as i8)),
+                            _mm_cmpeq_epi8(chunk, _mm_set1_epi8(b'\r' as i8))),
+                        _mm_cmpeq_epi8(chunk, _mm_set1_epi8(0x0c))));
+            _mm_xor_si128(ws, _mm_set1_epi8(-1))
+        }) {
+            (Some(hit), _) => return Some(hit),
+            (None, i) => i,
+        };
+        #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+        let i = 0;
+
+        return scan_swar(bytes, i,
+            |word| {
+                // needs exact masks, cause we negate.
+                let ws = equal_bytes_exact(word, b' ')
+                       | equal_bytes_exact(word, b'\t')
+                       | equal_bytes_exact(word, b'\n')
+                       | equal_bytes_exact(word, b'\r')
+                       | equal_bytes_exact(word, 0x0c);
+                ByteMask8::from_bytes(ws ^ splat_8(0x80))
+            },
+            |b| !b.is_ascii_whitespace());
     }
 }
 
+
 impl<'a, T> core::ops::Deref for Reader<'a, T> {
     type Target = [T];
 
@@ -495,5 +825,123 @@ mod tests {
         check_offset(&r, 6);
         assert!(r.is_empty());
     }
-}
 
+    #[test]
+    fn reader_numbers() {
+        let mut bytes = std::vec::Vec::new();
+        bytes.extend_from_slice(&(-2i16).to_le_bytes());
+        bytes.extend_from_slice(&(-3i32).to_be_bytes());
+        bytes.extend_from_slice(&i64::MIN.to_le_bytes());
+        bytes.extend_from_slice(&(-5i128).to_be_bytes());
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+        bytes.extend_from_slice(&(-0.25f64).to_be_bytes());
+        bytes.push(0xff);
+
+        let mut r = Reader::new(&bytes);
+        assert_eq!(r.next_i16_le(), Some(-2));
+        assert_eq!(r.next_i32_be(), Some(-3));
+        assert_eq!(r.try_next_i64_le(), Ok(i64::MIN));
+        assert_eq!(r.try_next_i128_be(), Ok(-5));
+        assert_eq!(r.next_f32_le(), Some(1.5));
+        assert_eq!(r.try_next_f64_be(), Ok(-0.25));
+
+        let offset = r.offset();
+        assert_eq!(r.try_next_u32_le(),
+            Err(ReadError { offset, needed: 4, available: 1 }));
+        assert_eq!(r.next_u16_be(), None);
+        assert_eq!(r.offset(), offset);
+        assert_eq!(r.try_next_n(2),
+            Err(ReadError { offset, needed: 2, available: 1 }));
+
+        assert_eq!(r.try_next_i8_le(), Ok(-1));
+        assert_eq!(r.try_next_u8_be(),
+            Err(ReadError { offset: offset + 1, needed: 1, available: 0 }));
+    }
+
+    #[test]
+    fn reader_checkpoint() {
+        let input = b"let x\r\n  = 42;\nfoo";
+        let mut r = Reader::new(input);
+
+        let start = r.checkpoint();
+        assert_eq!(r.consume_while(|at| at.is_ascii_alphabetic()), true);
+        assert_eq!(r.span_from(start), 0..3);
+        assert_eq!(r.slice_from(start), b"let");
+
+        let cp = r.checkpoint();
+        assert_eq!(r.with_backtrack(|r| {
+            r.consume(1);
+            r.expect(b'=').ok()
+        }), None);
+        assert_eq!(r.checkpoint(), cp);
+
+        assert_eq!(r.with_backtrack(|r| {
+            r.consume(1);
+            r.next()
+        }), Some(b'x'));
+        assert_eq!(r.offset(), 5);
+
+        r.rewind(cp);
+        assert_eq!(r.offset(), 3);
+        r.rewind(start);
+        assert_eq!(r.offset(), 0);
+
+        assert_eq!(r.line_col(0),  LineCol { line: 1, column: 1 });
+        assert_eq!(r.line_col(4),  LineCol { line: 1, column: 5 });
+        assert_eq!(r.line_col(6),  LineCol { line: 1, column: 7 });
+        assert_eq!(r.line_col(7),  LineCol { line: 2, column: 1 });
+        assert_eq!(r.line_col(9),  LineCol { line: 2, column: 3 });
+        assert_eq!(r.line_col(15), LineCol { line: 3, column: 1 });
+        assert_eq!(r.line_col(input.len()), LineCol { line: 3, column: 4 });
+    }
+
+    #[test]
+    fn reader_scan() {
+        // long enough for every chunk size.
+        let mut input = std::vec::Vec::new();
+        input.extend_from_slice(b" \t\r\n \x0c  \n\t\t      \r\n    x = 1; // comment ");
+        input.extend_from_slice(&[b'.'; 37]);
+        input.extend_from_slice(b"\nend");
+
+        let mut r = Reader::new(&input);
+        assert_eq!(r.find_byte(b'x'), Some(23));
+        assert_eq!(r.find_byte(b'#'), None);
+        assert_eq!(r.find_any(b"=;"), Some(25));
+        assert_eq!(r.find_any(b""), None);
+
+        assert_eq!(r.consume_ascii_whitespace(), true);
+        assert_eq!(r.peek(), Some(b'x'));
+
+        assert_eq!(r.consume_until_any(b"/;"), true);
+        assert_eq!(r.peek(), Some(b';'));
+        r.consume(1);
+
+        assert_eq!(r.consume_ascii_whitespace(), true);
+        assert_eq!(r.skip_until_newline(), true);
+        assert_eq!(r.peek(), Some(b'\n'));
+        r.consume(1);
+
+        assert_eq!(r.skip_until_newline(), false);
+        assert!(r.is_empty());
+
+        // compare against the scalar versions at every offset.
+        for offset in 0..input.len() {
+            let rest = &input[offset..];
+            let r = Reader::new(rest);
+            assert_eq!(r.find_byte(b'\n'), rest.iter().position(|b| *b == b'\n'));
+            assert_eq!(r.find_any(b"x/."), rest.iter().position(|b| b"x/.".contains(b)));
+
+            let mut r = Reader::new(rest);
+            let mut s = Reader::new(rest);
+            assert_eq!(r.consume_ascii_whitespace(), s.consume_while(|b| b.is_ascii_whitespace()));
+            assert_eq!(r.offset(), s.offset());
+        }
+
+        // swar false positives after a match.
+        let r = Reader::new(b"\x01\x00\x01\x01\x01\x01\x01\x01");
+        assert_eq!(r.find_byte(1), Some(0));
+        let mut r = Reader::new(b"  \x01 \x01      ");
+        assert_eq!(r.consume_ascii_whitespace(), true);
+        assert_eq!(r.offset(), 2);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::mem::{NonNull, PhantomData, ManuallyDrop};
+use crate::alloc::{Alloc, GlobalAlloc, Layout};
+use crate::atomic::{AtomicUsize, Ordering, fence};
+
+
+/// a thread safe reference counted pointer.
+///
+/// - the allocator is stored in the allocation,
+///   so `Arc` is a single pointer, and `A` need not be `Clone`.
+/// - see `unsize_arc!` for unsizing.
+pub struct Arc<T: ?Sized, A: Alloc = GlobalAlloc> {
+    inner: NonNull<ArcInner<T, A>>,
+    phantom: PhantomData<ArcInner<T, A>>,
+}
+
+/// a non-owning reference to an `Arc` allocation.
+///
+/// - see `Arc::downgrade`.
+pub struct Weak<T: ?Sized, A: Alloc = GlobalAlloc> {
+    inner: NonNull<ArcInner<T, A>>,
+}
+
+unsafe impl<T: ?Sized + Send + Sync, A: Alloc + Send + Sync> Send for Arc<T, A> {}
+unsafe impl<T: ?Sized + Send + Sync, A: Alloc + Send + Sync> Sync for Arc<T, A> {}
+unsafe impl<T: ?Sized + Send + Sync, A: Alloc + Send + Sync> Send for Weak<T, A> {}
+unsafe impl<T: ?Sized + Send + Sync, A: Alloc + Send + Sync> Sync for Weak<T, A> {}
+
+struct ArcHeader<A: Alloc> {
+    strong: AtomicUsize,
+
+    /// - the strong refs collectively hold one weak ref.
+    /// - `usize::MAX` while `get_mut` checks for uniqueness.
+    weak: AtomicUsize,
+
+    alloc: A,
+}
+
+#[repr(C)]
+struct ArcInner<T: ?Sized, A: Alloc> {
+    header: ArcHeader<A>,
+    value: T,
+}
+
+/// - guarantees the ref counts never overflow,
+///   even if many threads increment them at the same time.
+const MAX_REFS: usize = isize::MAX as usize;
+
+
+impl<T> Arc<T, GlobalAlloc> {
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Arc::new_in(GlobalAlloc, value)
+    }
+}
+
+impl<T, A: Alloc> Arc<T, A> {
+    pub fn new_in(alloc: A, value: T) -> Self {
+        let inner = crate::alloc::alloc_ptr::<ArcInner<T, A>>(&alloc).unwrap();
+        unsafe {
+            inner.as_ptr().write(ArcInner {
+                header: ArcHeader {
+                    strong: AtomicUsize::new(1),
+                    weak: AtomicUsize::new(1),
+                    alloc,
+                },
+                value,
+            });
+        }
+        return Arc { inner, phantom: PhantomData };
+    }
+
+    /// returns the value, if `this` is the last strong ref.
+    ///
+    /// - if multiple threads call `into_inner` on the last strong refs,
+    ///   exactly one of them gets the value.
+    /// - weak refs can no longer be upgraded.
+    pub fn into_inner(this: Self) -> Option<T> {
+        let this = ManuallyDrop::new(this);
+
+        if this.header().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return None;
+        }
+        fence(Ordering::Acquire);
+
+        unsafe {
+            let value = core::ptr::addr_of!((*this.inner.as_ptr()).value).read();
+            drop_weak(this.inner);
+            return Some(value);
+        }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Arc<T, A> {
+    #[inline(always)]
+    fn header(&self) -> &ArcHeader<A> {
+        unsafe { &self.inner.as_ref().header }
+    }
+
+    #[inline(always)]
+    pub fn strong_count(this: &Self) -> usize {
+        this.header().strong.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn weak_count(this: &Self) -> usize {
+        let weak = this.header().weak.load(Ordering::Relaxed);
+        // locked by `get_mut`, so there are no weak refs.
+        if weak == usize::MAX {
+            return 0;
+        }
+        return weak - 1;
+    }
+
+    /// the allocator, the value was allocated in.
+    #[inline(always)]
+    pub fn allocator(this: &Self) -> &A { &this.header().alloc }
+
+    #[inline(always)]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        this.inner.as_ptr() as *const u8 == other.inner.as_ptr() as *const u8
+    }
+
+    #[inline(always)]
+    pub fn as_ptr(this: &Self) -> NonNull<T> {
+        unsafe { NonNull::new_unchecked(core::ptr::addr_of_mut!((*this.inner.as_ptr()).value)) }
+    }
+
+    /// returns a mutable reference to the value,
+    /// if there are no other strong or weak refs.
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        let header = this.header();
+
+        // lock the weak count, so no weak refs can be created,
+        // while we check the strong count.
+        // the acquire syncs with the release in `Weak::drop`.
+        if header.weak.compare_exchange(1, usize::MAX, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            return None;
+        }
+
+        // the acquire syncs with the release in `Arc::drop`.
+        let unique = header.strong.load(Ordering::Acquire) == 1;
+        header.weak.store(1, Ordering::Release);
+
+        if unique {
+            return Some(unsafe { &mut this.inner.as_mut().value });
+        }
+        return None;
+    }
+
+    pub fn downgrade(this: &Self) -> Weak<T, A> {
+        let weak = &this.header().weak;
+        let mut cur = weak.load(Ordering::Relaxed);
+        loop {
+            // locked by `get_mut`.
+            if cur == usize::MAX {
+                core::hint::spin_loop();
+                cur = weak.load(Ordering::Relaxed);
+                continue;
+            }
+            assert!(cur < MAX_REFS);
+
+            match weak.compare_exchange_weak(cur, cur + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Weak { inner: this.inner },
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    /// consumes the `Arc` without decrementing the strong count.
+    ///
+    /// - see `from_raw`.
+    #[inline]
+    pub fn into_raw(this: Self) -> NonNull<T> {
+        let this = ManuallyDrop::new(this);
+        return Arc::as_ptr(&this);
+    }
+
+    /// # safety:
+    /// - `ptr` must have been returned by `Arc::<U, A>::into_raw`,
+    ///   where `U` is `T` or unsizes to `T`.
+    /// - each call to `into_raw` can be matched by at most one `from_raw`.
+    #[inline]
+    pub unsafe fn from_raw(ptr: NonNull<T>) -> Self {
+        unsafe {
+            let offset = value_offset::<A>(crate::mem::align_of_val(ptr.as_ref()));
+            let inner = ptr.as_ptr().byte_sub(offset) as *mut ArcInner<T, A>;
+            return Arc { inner: NonNull::new_unchecked(inner), phantom: PhantomData };
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn __into_unsize(this: Self) -> (NonNull<T>, PhantomData<A>) {
+        (Arc::into_raw(this), PhantomData)
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub unsafe fn __from_unsize(ptr: NonNull<T>, _: PhantomData<A>) -> Self {
+        unsafe { Arc::from_raw(ptr) }
+    }
+}
+
+/// the offset of `ArcInner::value`.
+#[inline(always)]
+fn value_offset<A: Alloc>(align: usize) -> usize {
+    crate::num::ceil_to_multiple_pow2(crate::mem::size_of::<ArcHeader<A>>(), align)
+}
+
+/// drops a weak ref.
+///
+/// # safety:
+/// - the caller must own a weak ref to `inner`.
+/// - if this is the last weak ref, the value must have been dropped.
+unsafe fn drop_weak<T: ?Sized, A: Alloc>(inner: NonNull<ArcInner<T, A>>) {
+    unsafe {
+        if inner.as_ref().header.weak.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        fence(Ordering::Acquire);
+
+        let layout = Layout::for_value(inner.as_ref());
+        let alloc = core::ptr::addr_of!((*inner.as_ptr()).header.alloc).read();
+        alloc.free(inner.cast(), layout);
+    }
+}
+
+
+impl<T: ?Sized, A: Alloc> Clone for Arc<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let old = self.header().strong.fetch_add(1, Ordering::Relaxed);
+        assert!(old < MAX_REFS);
+        return Arc { inner: self.inner, phantom: PhantomData };
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Drop for Arc<T, A> {
+    #[inline]
+    fn drop(&mut self) {
+        // the release makes our uses of the value happen before the drop.
+        if self.header().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        fence(Ordering::Acquire);
+
+        unsafe {
+            crate::mem::drop_in_place(&mut self.inner.as_mut().value);
+            drop_weak(self.inner);
+        }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> core::ops::Deref for Arc<T, A> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &self.inner.as_ref().value }
+    }
+}
+
+impl<T: ?Sized + core::fmt::Debug, A: Alloc> core::fmt::Debug for Arc<T, A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        (&**self).fmt(f)
+    }
+}
+
+
+impl<T: ?Sized, A: Alloc> Weak<T, A> {
+    /// returns a strong ref, if the value hasn't been dropped.
+    pub fn upgrade(&self) -> Option<Arc<T, A>> {
+        let strong = unsafe { &self.inner.as_ref().header.strong };
+        let mut cur = strong.load(Ordering::Relaxed);
+        loop {
+            if cur == 0 {
+                return None;
+            }
+            assert!(cur < MAX_REFS);
+
+            match strong.compare_exchange_weak(cur, cur + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Some(Arc { inner: self.inner, phantom: PhantomData }),
+                Err(old) => cur = old,
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn strong_count(&self) -> usize {
+        unsafe { self.inner.as_ref().header.strong.load(Ordering::Relaxed) }
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Clone for Weak<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        // can't be locked by `get_mut`, cause we hold a weak ref.
+        let old = unsafe { self.inner.as_ref().header.weak.fetch_add(1, Ordering::Relaxed) };
+        assert!(old < MAX_REFS);
+        return Weak { inner: self.inner };
+    }
+}
+
+impl<T: ?Sized, A: Alloc> Drop for Weak<T, A> {
+    #[inline]
+    fn drop(&mut self) {
+        // we own a weak ref.
+        // if it's the last one, the strong refs are gone,
+        // so the value has been dropped.
+        unsafe { drop_weak(self.inner) }
+    }
+}
+
+
+/// unsizes an `Arc`.
+///
+/// - usage: `let arc: Arc<dyn Trait> = unsize_arc!(Arc::new(value));`
+#[macro_export]
+macro_rules! unsize_arc {
+    ($arc:expr) => {{
+        let (ptr, alloc) = $crate::sync::arc::Arc::__into_unsize($arc);
+        // `ptr` is unsized by coercion.
+        unsafe { $crate::sync::arc::Arc::__from_unsize(ptr, alloc) }
+    }};
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arc_basic() {
+        let a = Arc::new(42);
+        assert_eq!(*a, 42);
+        assert_eq!(Arc::strong_count(&a), 1);
+        assert_eq!(Arc::weak_count(&a), 0);
+
+        let mut b = a.clone();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(Arc::strong_count(&a), 2);
+        assert!(Arc::get_mut(&mut b).is_none());
+
+        drop(a);
+        *Arc::get_mut(&mut b).unwrap() += 1;
+        assert_eq!(*b, 43);
+
+        let w = Arc::downgrade(&b);
+        assert_eq!(Arc::weak_count(&b), 1);
+        assert!(Arc::get_mut(&mut b).is_none());
+
+        let c = w.upgrade().unwrap();
+        assert_eq!(w.strong_count(), 2);
+        assert!(Arc::into_inner(c).is_none());
+
+        assert_eq!(Arc::into_inner(b), Some(43));
+        assert_eq!(w.strong_count(), 0);
+        assert!(w.upgrade().is_none());
+    }
+
+    #[test]
+    fn arc_raw_and_unsize() {
+        let a = Arc::new(std::string::String::from("hi"));
+        let raw = Arc::into_raw(a);
+        let a = unsafe { Arc::from_raw(raw) };
+        assert_eq!(*a, "hi");
+
+        let b: Arc<dyn core::fmt::Debug + Send + Sync> = crate::unsize_arc!(a.clone());
+        assert_eq!(std::format!("{:?}", b), "\"hi\"");
+        assert_eq!(Arc::strong_count(&a), 2);
+        drop(b);
+        assert_eq!(Arc::strong_count(&a), 1);
+
+        let s: Arc<[u64]> = crate::unsize_arc!(Arc::new([1u64, 2, 3]));
+        assert_eq!(&*s, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn arc_threads() {
+        let value = Arc::new(AtomicUsize::new(0));
+        let weak = Arc::downgrade(&value);
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let value = value.clone();
+                let weak = weak.clone();
+                s.spawn(move || {
+                    for _ in 0..1000 {
+                        let a = value.clone();
+                        a.fetch_add(1, Ordering::Relaxed);
+                        let b = weak.upgrade().unwrap();
+                        drop(Arc::downgrade(&b));
+                    }
+                });
+            }
+        });
+
+        assert_eq!(value.load(Ordering::Relaxed), 4000);
+        assert_eq!(Arc::strong_count(&value), 1);
+        assert_eq!(Arc::weak_count(&value), 1);
+
+        drop(weak);
+        let mut value = value;
+        assert!(Arc::get_mut(&mut value).is_some());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::atomic::Ordering;
+use crate::sync::futex::Futex;
+use crate::sync::mutex::MutexGuard;
+
+
+/// a condition variable.
+///
+/// - waits may wake up spuriously.
+///   use `wait_while` or check the condition in a loop.
+pub struct Condvar {
+    /// incremented on each notification.
+    seq: Futex,
+}
+
+impl Condvar {
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self { seq: Futex::new(0) }
+    }
+
+    /// unlocks the mutex, waits for a notification, then re-locks the mutex.
+    ///
+    /// - may return spuriously.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        // read the sequence number before unlocking,
+        // so notifications after unlocking aren't missed.
+        let seq = self.seq.load(Ordering::Relaxed);
+
+        let mutex = guard.mutex();
+        drop(guard);
+
+        self.seq.wait(seq);
+
+        return mutex.lock();
+    }
+
+    /// waits, while `cond` returns `true`.
+    pub fn wait_while<'a, T: ?Sized, F: FnMut(&mut T) -> bool>(&self, mut guard: MutexGuard<'a, T>, mut cond: F) -> MutexGuard<'a, T> {
+        while cond(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        return guard;
+    }
+
+    #[inline]
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        self.seq.wake_one();
+    }
+
+    #[inline]
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        self.seq.wake_all();
+    }
+}
+
+impl Default for Condvar {
+    #[inline(always)]
+    fn default() -> Self { Self::new() }
+}
+
+impl core::fmt::Debug for Condvar {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Condvar").finish_non_exhaustive()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::mutex::Mutex;
+
+    #[test]
+    fn condvar_queue() {
+        let queue = Mutex::new(std::vec::Vec::new());
+        let cv = Condvar::new();
+
+        let received = std::thread::scope(|s| {
+            let consumer = s.spawn(|| {
+                let mut received = std::vec::Vec::new();
+                loop {
+                    let mut q = cv.wait_while(queue.lock(), |q| q.is_empty());
+                    let v = q.remove(0);
+                    drop(q);
+                    if v == u32::MAX { break }
+                    received.push(v);
+                }
+                received
+            });
+
+            for i in 0..1000 {
+                queue.lock().push(i);
+                cv.notify_one();
+            }
+            queue.lock().push(u32::MAX);
+            cv.notify_all();
+
+            consumer.join().unwrap()
+        });
+
+        assert_eq!(received, (0..1000).collect::<std::vec::Vec<_>>());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::atomic::{AtomicU32, Ordering};
+
+
+/// a 32 bit atomic, that threads can wait on.
+///
+/// - on linux, this uses the futex syscall.
+/// - on other platforms with `std`, waiters are parked on
+///   a fixed set of mutex/condvar buckets.
+/// - without `std`, waiting just spins for a bit.
+///   this is valid, cause wait may wake up spuriously anyway.
+pub struct Futex {
+    value: AtomicU32,
+}
+
+impl Futex {
+    #[inline(always)]
+    pub const fn new(value: u32) -> Self {
+        Self { value: AtomicU32::new(value) }
+    }
+
+    /// blocks, while the value is `expected`.
+    ///
+    /// - returns immediately, if the value isn't `expected`.
+    /// - may return spuriously.
+    #[inline]
+    pub fn wait(&self, expected: u32) {
+        sys::wait(&self.value, expected)
+    }
+
+    /// wakes one waiting thread.
+    ///
+    /// - returns whether a thread was woken.
+    ///   returns `false`, if that's unknown.
+    #[inline]
+    pub fn wake_one(&self) -> bool {
+        sys::wake(&self.value, false)
+    }
+
+    /// wakes all waiting threads.
+    #[inline]
+    pub fn wake_all(&self) {
+        sys::wake(&self.value, true);
+    }
+}
+
+impl crate::ops::Deref for Futex {
+    type Target = AtomicU32;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target { &self.value }
+}
+
+impl core::fmt::Debug for Futex {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_tuple("Futex").field(&self.value.load(Ordering::Relaxed)).finish()
+    }
+}
+
+
+crate::cfg_if! {
+    if #[cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))] {
+        mod sys {
+            use crate::atomic::AtomicU32;
+
+            const FUTEX_WAIT: usize = 0;
+            const FUTEX_WAKE: usize = 1;
+            const FUTEX_PRIVATE_FLAG: usize = 128;
+
+            #[cfg(target_arch = "x86_64")]
+            const SYS_FUTEX: usize = 202;
+            #[cfg(target_arch = "aarch64")]
+            const SYS_FUTEX: usize = 98;
+
+            #[cfg(target_arch = "x86_64")]
+            #[inline(always)]
+            unsafe fn syscall4(n: usize, a0: usize, a1: usize, a2: usize, a3: usize) -> isize {
+                let result: isize;
+                unsafe {
+                    core::arch::asm!(
+                        "syscall",
+                        inlateout("rax") n as isize => result,
+                        in("rdi") a0, in("rsi") a1, in("rdx") a2, in("r10") a3,
+                        lateout("rcx") _, lateout("r11") _,
+                        options(nostack));
+                }
+                return result;
+            }
+
+            #[cfg(target_arch = "aarch64")]
+            #[inline(always)]
+            unsafe fn syscall4(n: usize, a0: usize, a1: usize, a2: usize, a3: usize) -> isize {
+                let result: isize;
+                unsafe {
+                    core::arch::asm!(
+                        "svc 0",
+                        in("x8") n,
+                        inlateout("x0") a0 as isize => result,
+                        in("x1") a1, in("x2") a2, in("x3") a3,
+                        options(nostack));
+                }
+                return result;
+            }
+
+            pub fn wait(futex: &AtomicU32, expected: u32) {
+                // errors (`EAGAIN`, `EINTR`) are spurious wake ups.
+                // the kernel reads the value atomically.
+                unsafe {
+                    syscall4(SYS_FUTEX,
+                        futex.as_ptr() as usize,
+                        FUTEX_WAIT | FUTEX_PRIVATE_FLAG,
+                        expected as usize,
+                        0);
+                }
+            }
+
+            pub fn wake(futex: &AtomicU32, all: bool) -> bool {
+                let n = if all { i32::MAX as usize } else { 1 };
+                let woken = unsafe {
+                    syscall4(SYS_FUTEX,
+                        futex.as_ptr() as usize,
+                        FUTEX_WAKE | FUTEX_PRIVATE_FLAG,
+                        n,
+                        0)
+                };
+                return woken > 0;
+            }
+        }
+    }
+    else if #[cfg(feature = "std")] {
+        mod sys {
+            use crate::atomic::{AtomicU32, Ordering};
+
+            struct Bucket {
+                mutex: std::sync::Mutex<()>,
+                condvar: std::sync::Condvar,
+            }
+
+            const NUM_BUCKETS: usize = 64;
+
+            static BUCKETS: [Bucket; NUM_BUCKETS] = [const {
+                Bucket { mutex: std::sync::Mutex::new(()), condvar: std::sync::Condvar::new() }
+            }; NUM_BUCKETS];
+
+            #[inline]
+            fn bucket(futex: &AtomicU32) -> &'static Bucket {
+                let addr = futex.as_ptr() as usize;
+                let hash = (addr >> 2).wrapping_mul(0x9e3779b97f4a7c15u64 as usize);
+                return &BUCKETS[hash >> (usize::BITS - NUM_BUCKETS.trailing_zeros())];
+            }
+
+            pub fn wait(futex: &AtomicU32, expected: u32) {
+                let bucket = bucket(futex);
+                let guard = bucket.mutex.lock().unwrap_or_else(|e| e.into_inner());
+                // wakers lock the bucket, so they can't
+                // notify between the check and the wait.
+                if futex.load(Ordering::SeqCst) != expected {
+                    return;
+                }
+                drop(bucket.condvar.wait(guard));
+            }
+
+            pub fn wake(futex: &AtomicU32, _all: bool) -> bool {
+                let bucket = bucket(futex);
+                drop(bucket.mutex.lock().unwrap_or_else(|e| e.into_inner()));
+                // buckets are shared, so we have to wake everyone.
+                bucket.condvar.notify_all();
+                return false;
+            }
+        }
+    }
+    else {
+        mod sys {
+            use crate::atomic::{AtomicU32, Ordering};
+
+            pub fn wait(futex: &AtomicU32, expected: u32) {
+                for _ in 0..1024 {
+                    if futex.load(Ordering::Relaxed) != expected {
+                        return;
+                    }
+                    crate::hint::spin_loop();
+                }
+            }
+
+            pub fn wake(_futex: &AtomicU32, _all: bool) -> bool {
+                return false;
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn futex_wait_wake() {
+        let futex = Futex::new(0);
+
+        // not equal -> returns immediately.
+        futex.wait(1);
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                while futex.load(Ordering::Acquire) == 0 {
+                    futex.wait(0);
+                }
+            });
+
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            futex.store(1, Ordering::Release);
+            futex.wake_all();
+        });
+
+        assert_eq!(futex.load(Ordering::Relaxed), 1);
+    }
+}
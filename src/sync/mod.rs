@@ -0,0 +1,37 @@
+pub mod arc;
+pub mod futex;
+pub mod mutex;
+pub mod rwlock;
+pub mod condvar;
+
+
+pub struct AssertSync<T>(T);
+
+impl<T> AssertSync<T> {
+    #[inline]
+    pub const unsafe fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    #[inline]
+    pub const fn get(&self) -> &T {
+        &self.0
+    }
+}
+
+unsafe impl<T> Sync for AssertSync<T> {}
+
+
+
+/// aborts, if the current thread is panicking.
+///
+/// - used by lock guards, which don't support poisoning.
+///   unwinding out of a critical section could leave
+///   the protected data in an inconsistent state.
+#[inline]
+pub(crate) fn abort_if_panicking() {
+    #[cfg(feature="std")]
+    if std::thread::panicking() {
+        std::process::abort();
+    }
+}
@@ -0,0 +1,194 @@
+use crate::mem::{UnsafeCell, PhantomData};
+use crate::atomic::Ordering;
+use crate::sync::futex::Futex;
+
+
+/// a mutual exclusion lock.
+///
+/// - spins for a bit, then waits on a futex.
+/// - no poisoning. guards abort, if they're dropped during a panic.
+pub struct Mutex<T: ?Sized> {
+    state: Futex,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// - locked, and there may be waiting threads.
+const CONTENDED: u32 = 2;
+
+impl<T> Mutex<T> {
+    #[inline(always)]
+    pub const fn new(value: T) -> Self {
+        Self { state: Futex::new(UNLOCKED), value: UnsafeCell::new(value) }
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    #[inline]
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        if self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            self.lock_contended();
+        }
+        return MutexGuard { mutex: self, phantom: PhantomData };
+    }
+
+    #[inline]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+            return Some(MutexGuard { mutex: self, phantom: PhantomData });
+        }
+        return None;
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    #[cold]
+    fn lock_contended(&self) {
+        let mut state = self.spin();
+
+        if state == UNLOCKED {
+            match self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return,
+                Err(s) => state = s,
+            }
+        }
+
+        loop {
+            // we don't know, whether there are other waiters,
+            // so we have to lock as contended.
+            if state != CONTENDED && self.state.swap(CONTENDED, Ordering::Acquire) == UNLOCKED {
+                return;
+            }
+
+            self.state.wait(CONTENDED);
+            state = self.spin();
+        }
+    }
+
+    #[inline]
+    fn spin(&self) -> u32 {
+        let mut spin = 100;
+        loop {
+            let state = self.state.load(Ordering::Relaxed);
+            if state != LOCKED || spin == 0 {
+                return state;
+            }
+            crate::hint::spin_loop();
+            spin -= 1;
+        }
+    }
+
+    /// # safety:
+    /// - the mutex must be locked by the caller.
+    #[inline]
+    unsafe fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            self.state.wake_one();
+        }
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    #[inline]
+    fn default() -> Self { Self::new(T::default()) }
+}
+
+impl<T: ?Sized + core::fmt::Debug> core::fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let mut d = f.debug_struct("Mutex");
+        match self.try_lock() {
+            Some(guard) => { d.field("value", &&*guard); }
+            None => { d.field("value", &format_args!("<locked>")); }
+        }
+        d.finish()
+    }
+}
+
+
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+    // guards must be dropped on the locking thread.
+    phantom: PhantomData<*const ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for MutexGuard<'a, T> {}
+
+impl<'a, T: ?Sized> MutexGuard<'a, T> {
+    /// the mutex, that's locked by this guard.
+    #[inline(always)]
+    pub fn mutex(&self) -> &'a Mutex<T> { self.mutex }
+}
+
+impl<'a, T: ?Sized> crate::ops::Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> crate::ops::DerefMut for MutexGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        crate::sync::abort_if_panicking();
+
+        // we hold the lock.
+        unsafe { self.mutex.unlock() }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutex_basic() {
+        let mut mutex = Mutex::new(1);
+        {
+            let mut guard = mutex.lock();
+            *guard += 1;
+            assert!(mutex.try_lock().is_none());
+        }
+        assert_eq!(*mutex.try_lock().unwrap(), 2);
+        *mutex.get_mut() += 1;
+        assert_eq!(mutex.into_inner(), 3);
+    }
+
+    #[test]
+    fn mutex_threads() {
+        let mutex = Mutex::new(0u64);
+
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..10_000 {
+                        *mutex.lock() += 1;
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*mutex.lock(), 80_000);
+    }
+}
@@ -0,0 +1,419 @@
+use crate::mem::{UnsafeCell, PhantomData};
+use crate::atomic::Ordering;
+use crate::sync::futex::Futex;
+
+
+/// a reader-writer lock.
+///
+/// - writer preferring: new readers block, while writers are waiting.
+/// - spins for a bit, then waits on a futex.
+/// - no poisoning. guards abort, if they're dropped during a panic.
+pub struct RwLock<T: ?Sized> {
+    /// - bits 0..30: the number of readers, or `WRITE_LOCKED`.
+    /// - bit 30: readers are waiting.
+    /// - bit 31: writers are waiting.
+    state: Futex,
+
+    /// incremented, when a writer is woken.
+    writer_notify: Futex,
+
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+const READ_LOCKED: u32 = 1;
+const MASK: u32 = (1 << 30) - 1;
+const WRITE_LOCKED: u32 = MASK;
+const MAX_READERS: u32 = MASK - 1;
+const READERS_WAITING: u32 = 1 << 30;
+const WRITERS_WAITING: u32 = 1 << 31;
+
+#[inline(always)]
+fn is_unlocked(state: u32) -> bool { state & MASK == 0 }
+
+#[inline(always)]
+fn is_write_locked(state: u32) -> bool { state & MASK == WRITE_LOCKED }
+
+#[inline(always)]
+fn has_readers_waiting(state: u32) -> bool { state & READERS_WAITING != 0 }
+
+#[inline(always)]
+fn has_writers_waiting(state: u32) -> bool { state & WRITERS_WAITING != 0 }
+
+#[inline(always)]
+fn is_read_lockable(state: u32) -> bool {
+    // readers don't lock, while others are waiting.
+    // this gives writers preference.
+    state & MASK < MAX_READERS && !has_readers_waiting(state) && !has_writers_waiting(state)
+}
+
+
+impl<T> RwLock<T> {
+    #[inline(always)]
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: Futex::new(0),
+            writer_notify: Futex::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    #[inline]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        let state = self.state.load(Ordering::Relaxed);
+        if !is_read_lockable(state)
+        || self.state.compare_exchange_weak(state, state + READ_LOCKED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            self.read_contended();
+        }
+        return RwLockReadGuard { lock: self, phantom: PhantomData };
+    }
+
+    #[inline]
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let ok = self.state.fetch_update(Ordering::Acquire, Ordering::Relaxed, |s|
+            is_read_lockable(s).then_some(s + READ_LOCKED)).is_ok();
+        if !ok {
+            return None;
+        }
+        return Some(RwLockReadGuard { lock: self, phantom: PhantomData });
+    }
+
+    #[inline]
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        if self.state.compare_exchange_weak(0, WRITE_LOCKED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            self.write_contended();
+        }
+        return RwLockWriteGuard { lock: self, phantom: PhantomData };
+    }
+
+    #[inline]
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let ok = self.state.fetch_update(Ordering::Acquire, Ordering::Relaxed, |s|
+            is_unlocked(s).then_some(s + WRITE_LOCKED)).is_ok();
+        if !ok {
+            return None;
+        }
+        return Some(RwLockWriteGuard { lock: self, phantom: PhantomData });
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+
+    #[cold]
+    fn read_contended(&self) {
+        let mut state = self.spin_read();
+
+        loop {
+            if is_read_lockable(state) {
+                match self.state.compare_exchange_weak(state, state + READ_LOCKED, Ordering::Acquire, Ordering::Relaxed) {
+                    Ok(_) => return,
+                    Err(s) => { state = s; continue }
+                }
+            }
+
+            if state & MASK == MAX_READERS {
+                panic!("too many active read locks on RwLock");
+            }
+
+            if !has_readers_waiting(state) {
+                if let Err(s) = self.state.compare_exchange(state, state | READERS_WAITING, Ordering::Relaxed, Ordering::Relaxed) {
+                    state = s;
+                    continue;
+                }
+            }
+
+            self.state.wait(state | READERS_WAITING);
+            state = self.spin_read();
+        }
+    }
+
+    #[cold]
+    fn write_contended(&self) {
+        let mut state = self.spin_write();
+
+        // once we've waited, there may be other waiting writers,
+        // which rely on us to preserve the flag.
+        let mut other_writers_waiting = 0;
+
+        loop {
+            if is_unlocked(state) {
+                match self.state.compare_exchange_weak(state, state | WRITE_LOCKED | other_writers_waiting, Ordering::Acquire, Ordering::Relaxed) {
+                    Ok(_) => return,
+                    Err(s) => { state = s; continue }
+                }
+            }
+
+            if !has_writers_waiting(state) {
+                if let Err(s) = self.state.compare_exchange(state, state | WRITERS_WAITING, Ordering::Relaxed, Ordering::Relaxed) {
+                    state = s;
+                    continue;
+                }
+            }
+
+            other_writers_waiting = WRITERS_WAITING;
+
+            // read the notify counter before re-checking the state,
+            // so we don't miss a wake up in between.
+            let seq = self.writer_notify.load(Ordering::Acquire);
+
+            state = self.state.load(Ordering::Relaxed);
+            if is_unlocked(state) || !has_writers_waiting(state) {
+                continue;
+            }
+
+            self.writer_notify.wait(seq);
+            state = self.spin_write();
+        }
+    }
+
+    /// # safety:
+    /// - the lock must be read locked by the caller.
+    #[inline]
+    unsafe fn read_unlock(&self) {
+        let state = self.state.fetch_sub(READ_LOCKED, Ordering::Release) - READ_LOCKED;
+
+        // readers only wait, while others hold or wait for the write lock.
+        debug_assert!(!has_readers_waiting(state) || has_writers_waiting(state));
+
+        if is_unlocked(state) && has_writers_waiting(state) {
+            self.wake_writer_or_readers(state);
+        }
+    }
+
+    /// # safety:
+    /// - the lock must be write locked by the caller.
+    #[inline]
+    unsafe fn write_unlock(&self) {
+        let state = self.state.fetch_sub(WRITE_LOCKED, Ordering::Release) - WRITE_LOCKED;
+        debug_assert!(is_unlocked(state));
+
+        if has_writers_waiting(state) || has_readers_waiting(state) {
+            self.wake_writer_or_readers(state);
+        }
+    }
+
+    #[cold]
+    fn wake_writer_or_readers(&self, mut state: u32) {
+        assert!(is_unlocked(state));
+
+        // only writers waiting.
+        if state == WRITERS_WAITING {
+            match self.state.compare_exchange(state, 0, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => { self.wake_writer(); return }
+                Err(s) => state = s,
+            }
+        }
+
+        // both waiting: wake a writer, readers keep waiting.
+        if state == READERS_WAITING + WRITERS_WAITING {
+            if self.state.compare_exchange(state, READERS_WAITING, Ordering::Relaxed, Ordering::Relaxed).is_err() {
+                // someone else locked, they'll wake the waiters.
+                return;
+            }
+            if self.wake_writer() {
+                return;
+            }
+            // no writers were actually waiting.
+            state = READERS_WAITING;
+        }
+
+        // only readers waiting.
+        if state == READERS_WAITING {
+            if self.state.compare_exchange(state, 0, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+                self.state.wake_all();
+            }
+        }
+    }
+
+    #[inline]
+    fn wake_writer(&self) -> bool {
+        self.writer_notify.fetch_add(1, Ordering::Release);
+        return self.writer_notify.wake_one();
+    }
+
+    #[inline]
+    fn spin_until(&self, f: impl Fn(u32) -> bool) -> u32 {
+        let mut spin = 100;
+        loop {
+            let state = self.state.load(Ordering::Relaxed);
+            if f(state) || spin == 0 {
+                return state;
+            }
+            crate::hint::spin_loop();
+            spin -= 1;
+        }
+    }
+
+    #[inline]
+    fn spin_read(&self) -> u32 {
+        self.spin_until(|s| !is_write_locked(s) || has_readers_waiting(s) || has_writers_waiting(s))
+    }
+
+    #[inline]
+    fn spin_write(&self) -> u32 {
+        self.spin_until(|s| is_unlocked(s) || has_writers_waiting(s))
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    #[inline]
+    fn default() -> Self { Self::new(T::default()) }
+}
+
+impl<T: ?Sized + core::fmt::Debug> core::fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let mut d = f.debug_struct("RwLock");
+        match self.try_read() {
+            Some(guard) => { d.field("value", &&*guard); }
+            None => { d.field("value", &format_args!("<locked>")); }
+        }
+        d.finish()
+    }
+}
+
+
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    // guards must be dropped on the locking thread.
+    phantom: PhantomData<*const ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for RwLockReadGuard<'a, T> {}
+
+impl<'a, T: ?Sized> crate::ops::Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockReadGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        crate::sync::abort_if_panicking();
+
+        // we hold a read lock.
+        unsafe { self.lock.read_unlock() }
+    }
+}
+
+
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    // guards must be dropped on the locking thread.
+    phantom: PhantomData<*const ()>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for RwLockWriteGuard<'a, T> {}
+
+impl<'a, T: ?Sized> crate::ops::Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> crate::ops::DerefMut for RwLockWriteGuard<'a, T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        crate::sync::abort_if_panicking();
+
+        // we hold the write lock.
+        unsafe { self.lock.write_unlock() }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rwlock_basic() {
+        let mut lock = RwLock::new(1);
+        {
+            let a = lock.read();
+            let b = lock.read();
+            assert_eq!(*a + *b, 2);
+            assert!(lock.try_write().is_none());
+        }
+        {
+            let mut w = lock.write();
+            *w += 1;
+            assert!(lock.try_read().is_none());
+            assert!(lock.try_write().is_none());
+        }
+        assert_eq!(*lock.try_read().unwrap(), 2);
+        *lock.get_mut() += 1;
+        assert_eq!(lock.into_inner(), 3);
+    }
+
+    #[test]
+    fn rwlock_threads() {
+        let lock = RwLock::new((0u64, 0u64));
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..5_000 {
+                        let mut w = lock.write();
+                        w.0 += 1;
+                        w.1 += 1;
+                    }
+                });
+                s.spawn(|| {
+                    for _ in 0..5_000 {
+                        let r = lock.read();
+                        assert_eq!(r.0, r.1);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(*lock.read(), (20_000, 20_000));
+    }
+
+    #[test]
+    fn rwlock_writer_preference() {
+        let lock = RwLock::new(0);
+
+        let r = lock.read();
+        std::thread::scope(|s| {
+            s.spawn(|| { *lock.write() += 1; });
+
+            // wait for the writer to block.
+            while !has_writers_waiting(lock.state.load(Ordering::Relaxed)) {
+                std::thread::yield_now();
+            }
+
+            // new readers can't lock, while a writer is waiting.
+            assert!(lock.try_read().is_none());
+            drop(r);
+        });
+
+        assert_eq!(*lock.read(), 1);
+    }
+}
@@ -0,0 +1,887 @@
+use crate::ext::FromIn;
+use crate::alloc::{Alloc, GlobalAlloc, Layout};
+use crate::mem::{NonNull, PhantomData, MaybeUninit};
+use crate::key::Key;
+use crate::ops::{Bound, RangeBounds};
+use crate::vec::KVec;
+
+
+pub type VecDeque<V, A = GlobalAlloc> = KVecDeque<u32, V, A>;
+
+pub type ZVecDeque<V, A = GlobalAlloc> = KVecDeque<usize, V, A>;
+
+/// a growable ring buffer.
+///
+/// - the values are stored at `(head + i) % cap`.
+/// - the unused slots are asan poisoned.
+pub struct KVecDeque<K: Key, V, A: Alloc = GlobalAlloc> {
+    alloc: A,
+    cap: K,
+    /// - head < cap || cap == 0
+    head: K,
+    len: K,
+    ptr: NonNull<V>,
+    phantom: PhantomData<V>,
+}
+
+impl<K: Key, V> KVecDeque<K, V, GlobalAlloc> {
+    #[inline(always)]
+    pub const fn new() -> Self {
+        Self::new_in(GlobalAlloc)
+    }
+
+    #[inline(always)]
+    pub fn with_cap(cap: usize) -> Self {
+        Self::with_cap_in(GlobalAlloc, cap)
+    }
+}
+
+impl<K: Key, V, A: Alloc> KVecDeque<K, V, A> {
+    #[inline]
+    pub const fn new_in(alloc: A) -> Self {
+        Self {
+            alloc,
+            cap: K::MIN,
+            head: K::MIN,
+            len: K::MIN,
+            ptr: NonNull::dangling(),
+            phantom: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn with_cap_in(alloc: A, cap: usize) -> Self {
+        let mut this = Self::new_in(alloc);
+        unsafe { this.set_cap(cap) };
+        return this;
+    }
+
+
+    #[inline(always)]
+    pub fn alloc(&self) -> &A { &self.alloc }
+
+    #[inline(always)]
+    pub fn cap(&self) -> usize { self.cap.usize() }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize { self.len.usize() }
+
+    #[inline(always)]
+    pub fn klen(&self) -> K { self.len }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool { self.len.usize() == 0 }
+
+
+    /// the physical index of the `i`-th value.
+    /// - `i <= self.cap`.
+    #[inline(always)]
+    fn phys(&self, i: usize) -> usize {
+        let result = self.head.usize() + i;
+        if result >= self.cap.usize() { result - self.cap.usize() } else { result }
+    }
+
+    #[inline(always)]
+    fn slot(&self, phys: usize) -> *mut V {
+        unsafe { self.ptr.as_ptr().add(phys) }
+    }
+
+    /// the initialized ranges, `[head, head + len0)` and `[0, len1)`.
+    #[inline]
+    fn ranges(&self) -> (usize, usize) {
+        let head = self.head.usize();
+        let len = self.len.usize();
+        let len0 = len.min(self.cap.usize() - head);
+        return (len0, len - len0);
+    }
+
+    /// the unused ranges, `[begin0, begin0 + len0)` and `[0, len1)`.
+    #[inline]
+    fn gap(&self) -> (usize, usize, usize) {
+        let cap = self.cap.usize();
+        let head = self.head.usize();
+        let end = head + self.len.usize();
+        if end >= cap {
+            // wrapped: the gap is contiguous.
+            return (end - cap, head - (end - cap), 0);
+        }
+        return (end, cap - end, head);
+    }
+
+    #[inline]
+    fn poison_gap(&self) {
+        let (begin, len0, len1) = self.gap();
+        crate::asan::poison_ptr_len(self.slot(begin), len0);
+        crate::asan::poison_ptr_len(self.slot(0), len1);
+    }
+
+    #[inline]
+    fn unpoison_gap(&self) {
+        let (begin, len0, len1) = self.gap();
+        crate::asan::unpoison_ptr_len(self.slot(begin), len0);
+        crate::asan::unpoison_ptr_len(self.slot(0), len1);
+    }
+
+
+    #[inline]
+    pub fn as_slices(&self) -> (&[V], &[V]) { unsafe {
+        let (len0, len1) = self.ranges();
+        (crate::slice::from_raw_parts(self.slot(self.head.usize()), len0),
+         crate::slice::from_raw_parts(self.slot(0), len1))
+    }}
+
+    #[inline]
+    pub fn as_mut_slices(&mut self) -> (&mut [V], &mut [V]) { unsafe {
+        let (len0, len1) = self.ranges();
+        (crate::slice::from_raw_parts_mut(self.slot(self.head.usize()), len0),
+         crate::slice::from_raw_parts_mut(self.slot(0), len1))
+    }}
+
+    #[inline]
+    pub fn get(&self, idx: K) -> Option<&V> {
+        if idx < self.len {
+            return Some(unsafe { &*self.slot(self.phys(idx.usize())) });
+        }
+        return None;
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, idx: K) -> Option<&mut V> {
+        if idx < self.len {
+            return Some(unsafe { &mut *self.slot(self.phys(idx.usize())) });
+        }
+        return None;
+    }
+
+    #[inline]
+    pub fn front(&self) -> Option<&V> { self.get(K::MIN) }
+
+    #[inline]
+    pub fn front_mut(&mut self) -> Option<&mut V> { self.get_mut(K::MIN) }
+
+    #[inline]
+    pub fn back(&self) -> Option<&V> {
+        if self.len.usize() > 0 { self.get(unsafe { self.len.sub(1) }) } else { None }
+    }
+
+    #[inline]
+    pub fn back_mut(&mut self) -> Option<&mut V> {
+        if self.len.usize() > 0 { self.get_mut(unsafe { self.len.sub(1) }) } else { None }
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, V> {
+        let (a, b) = self.as_slices();
+        a.iter().chain(b.iter())
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, V> {
+        let (a, b) = self.as_mut_slices();
+        a.iter_mut().chain(b.iter_mut())
+    }
+
+
+    /// set the deque's capacity.
+    ///
+    /// # safety:
+    /// - `self.len <= new_cap`.
+    ///
+    unsafe fn set_cap(&mut self, new_cap: usize) {
+        assert!(self.len.usize() <= new_cap);
+
+        let old_cap = self.cap.usize();
+        if new_cap == old_cap {
+            return;
+        }
+
+        // ensure we don't overflow K or the max allocation size.
+        assert!(new_cap <= KVec::<K, V, A>::CAP_MAX);
+
+        // shrinking: make sure all values are in `[0, len)`.
+        if new_cap < old_cap {
+            self.make_contiguous();
+            if self.head.usize() > 0 {
+                unsafe { self.move_to_front() };
+            }
+        }
+
+        self.unpoison_gap();
+
+        let new_ptr = unsafe {
+            let old_layout = Layout::array::<V>(old_cap).unwrap_unchecked();
+            let new_layout = Layout::array::<V>(new_cap).unwrap_unchecked();
+            self.alloc.realloc(self.ptr.cast(), old_layout, new_layout).unwrap().cast()
+        };
+        self.ptr = new_ptr;
+        self.cap = unsafe { K::from_usize_unck(new_cap) };
+
+        // the allocator may have grown the block in place,
+        // without unpoisoning the new part.
+        crate::asan::unpoison_ptr_len(self.slot(0), new_cap);
+
+        // growing: fix up wrapped values.
+        let head = self.head.usize();
+        if new_cap > old_cap && head + self.len.usize() > old_cap {
+            let head_len = old_cap - head;
+            let tail_len = self.len.usize() - head_len;
+            let grown = new_cap - old_cap;
+
+            unsafe {
+                if tail_len <= head_len && tail_len <= grown {
+                    // move the tail after the old end.
+                    crate::mem::copy_nonoverlapping(
+                        self.slot(0), self.slot(old_cap), tail_len);
+                }
+                else {
+                    // move the head segment to the new end.
+                    let new_head = new_cap - head_len;
+                    crate::mem::copy(self.slot(head), self.slot(new_head), head_len);
+                    self.head = K::from_usize_unck(new_head);
+                }
+            }
+        }
+        if self.head.usize() >= new_cap {
+            self.head = K::MIN;
+        }
+
+        self.poison_gap();
+    }
+
+    /// moves the values from `[head, head + len)` to `[0, len)`.
+    ///
+    /// # safety:
+    /// - the values must be contiguous.
+    unsafe fn move_to_front(&mut self) {
+        let head = self.head.usize();
+        let len = self.len.usize();
+        debug_assert!(head + len <= self.cap.usize());
+
+        self.unpoison_gap();
+        unsafe { crate::mem::copy(self.slot(head), self.slot(0), len) };
+        self.head = K::MIN;
+        self.poison_gap();
+    }
+
+    pub fn trim_exact(&mut self) {
+        // `new_cap >= self.len`.
+        unsafe { self.set_cap(self.len.usize()) }
+    }
+
+    pub fn reserve(&mut self, min_cap: usize) {
+        let new_cap = min_cap;
+        if new_cap > self.cap.usize() {
+            let new_cap =
+                if crate::mem::size_of::<V>() > 0 {
+                    // can't overflow, cause `self.cap <= isize::MAX/sizeof(T)`.
+                    new_cap.max(2*self.cap.usize())
+                }
+                else { new_cap };
+
+            let new_cap = new_cap.max(KVec::<K, V, A>::GROW_MIN_CAP);
+
+            // `new_cap > self.cap >= self.len`.
+            unsafe { self.set_cap(new_cap) };
+        }
+    }
+
+    pub fn reserve_exact(&mut self, cap: usize) {
+        if cap > self.cap.usize() {
+            // `cap > self.cap >= self.len`.
+            unsafe { self.set_cap(cap) };
+        }
+    }
+
+    pub fn reserve_more(&mut self, extra: usize) {
+        self.reserve(self.len.usize().checked_add(extra).unwrap());
+    }
+
+    #[cold]
+    fn reserve_one_more(&mut self) {
+        self.reserve_more(1);
+    }
+
+
+    /// appends `value` and returns its index.
+    #[inline]
+    pub fn push_back(&mut self, value: V) -> K {
+        if self.len == self.cap {
+            self.reserve_one_more();
+        }
+
+        let idx = self.len;
+        unsafe {
+            let ptr = self.slot(self.phys(idx.usize()));
+            crate::asan::unpoison_ptr(ptr);
+            ptr.write(value);
+            self.len = idx.add(1);
+        }
+        return idx;
+    }
+
+    /// prepends `value`.
+    ///
+    /// - the indices of the other values are incremented.
+    #[inline]
+    pub fn push_front(&mut self, value: V) {
+        if self.len == self.cap {
+            self.reserve_one_more();
+        }
+
+        unsafe {
+            let head = self.head.usize();
+            let new_head = if head > 0 { head - 1 } else { self.cap.usize() - 1 };
+
+            let ptr = self.slot(new_head);
+            crate::asan::unpoison_ptr(ptr);
+            ptr.write(value);
+
+            self.head = K::from_usize_unck(new_head);
+            self.len = self.len.add(1);
+        }
+    }
+
+    #[inline]
+    pub fn pop_back(&mut self) -> Option<V> {
+        if self.len.usize() > 0 { unsafe {
+            let last = self.len.sub(1);
+
+            let ptr = self.slot(self.phys(last.usize()));
+            let result = ptr.read();
+            crate::asan::poison_ptr(ptr);
+
+            self.len = last;
+            return Some(result);
+        }}
+        else { None }
+    }
+
+    #[inline]
+    pub fn pop_front(&mut self) -> Option<V> {
+        if self.len.usize() > 0 { unsafe {
+            let ptr = self.slot(self.head.usize());
+            let result = ptr.read();
+            crate::asan::poison_ptr(ptr);
+
+            self.head = K::from_usize_unck(self.phys(1));
+            self.len = self.len.sub(1);
+            return Some(result);
+        }}
+        else { None }
+    }
+
+    pub fn truncate(&mut self, new_len: usize) {
+        assert!(new_len <= self.len.usize());
+
+        while self.len.usize() > new_len {
+            drop(self.pop_back());
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.truncate(0);
+        self.head = K::MIN;
+    }
+
+
+    /// rearranges the values, so they're contiguous.
+    ///
+    /// - returns the values as a slice.
+    pub fn make_contiguous(&mut self) -> &mut [V] {
+        let (_, tail_len) = self.ranges();
+        if tail_len > 0 {
+            let head = self.head.usize();
+
+            // `[tail] gap [head]` -> `[head][tail] gap`.
+            // rotation only moves the slots, so the gap can be uninit.
+            self.unpoison_gap();
+            unsafe {
+                crate::slice::from_raw_parts_mut(
+                    self.slot(0).cast::<MaybeUninit<V>>(), self.cap.usize())
+                .rotate_left(head);
+            }
+            self.head = K::MIN;
+            self.poison_gap();
+        }
+
+        let head = self.head.usize();
+        unsafe { crate::slice::from_raw_parts_mut(self.slot(head), self.len.usize()) }
+    }
+}
+
+impl<K: Key, V, A: Alloc> KVecDeque<K, V, A> {
+    /// rotates the deque `n` places to the left.
+    ///
+    /// - the value at index `n` becomes the first value.
+    pub fn rotate_left(&mut self, n: usize) {
+        let len = self.len.usize();
+        assert!(n <= len);
+
+        if self.len == self.cap {
+            // no gap, just move the head.
+            self.head = unsafe { K::from_usize_unck(self.phys(n)) };
+        }
+        else if n <= len - n {
+            // `pop_front` leaves space for `push_back`.
+            for _ in 0..n {
+                let value = self.pop_front().unwrap();
+                self.push_back(value);
+            }
+        }
+        else {
+            self.rotate_right(len - n);
+        }
+    }
+
+    /// rotates the deque `n` places to the right.
+    ///
+    /// - the value at index `len - n` becomes the first value.
+    pub fn rotate_right(&mut self, n: usize) {
+        let len = self.len.usize();
+        assert!(n <= len);
+
+        if self.len == self.cap {
+            // no gap, just move the head.
+            self.head = unsafe { K::from_usize_unck(self.phys(len - n)) };
+        }
+        else if n <= len - n {
+            // `pop_back` leaves space for `push_front`.
+            for _ in 0..n {
+                let value = self.pop_back().unwrap();
+                self.push_front(value);
+            }
+        }
+        else {
+            self.rotate_left(len - n);
+        }
+    }
+
+    /// removes the values in `range` and returns them as an iterator.
+    ///
+    /// - the values are removed, even if the iterator isn't consumed.
+    /// - if the iterator is leaked, the values after `range.start` are leaked.
+    pub fn drain<R: RangeBounds<K>>(&mut self, range: R) -> Drain<'_, K, V, A> {
+        let len = self.len.usize();
+
+        let begin = match range.start_bound() {
+            Bound::Included(k) => k.usize(),
+            Bound::Excluded(k) => k.usize() + 1,
+            Bound::Unbounded   => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(k) => k.usize() + 1,
+            Bound::Excluded(k) => k.usize(),
+            Bound::Unbounded   => len,
+        };
+        assert!(begin <= end && end <= len);
+
+        // `begin <= len`.
+        self.len = unsafe { K::from_usize_unck(begin) };
+
+        return Drain { deque: self, begin, idx: begin, back: end, end, old_len: len };
+    }
+
+
+    pub fn clone_in<B: Alloc>(&self, alloc: B) -> KVecDeque<K, V, B>
+    where V: Clone {
+        let mut this = KVecDeque::with_cap_in(alloc, self.len.usize());
+        for v in self.iter() {
+            this.push_back(v.clone());
+        }
+        return this;
+    }
+}
+
+unsafe impl<K: Key, V: Sync, A: Alloc + Sync> Sync for KVecDeque<K, V, A> {}
+unsafe impl<K: Key, V: Send, A: Alloc + Send> Send for KVecDeque<K, V, A> {}
+
+
+impl<K: Key, V, A: Alloc> Drop for KVecDeque<K, V, A> {
+    fn drop(&mut self) { unsafe {
+        // drop values.
+        let (len0, len1) = self.ranges();
+        crate::mem::drop_in_place(
+            crate::slice::from_raw_parts_mut(self.slot(self.head.usize()), len0));
+        crate::mem::drop_in_place(
+            crate::slice::from_raw_parts_mut(self.slot(0), len1));
+
+        // `self.cap` is always valid for `Layout::array`.
+        let layout = Layout::array::<V>(self.cap.usize()).unwrap_unchecked();
+
+        // asan::poison is done by alloc.free.
+
+        // `self.ptr` is an allocation iff `self.cap > 0`.
+        self.alloc.free(self.ptr.cast(), layout);
+    }}
+}
+
+
+impl<K: Key, V, A: Alloc> crate::ops::Index<K> for KVecDeque<K, V, A> {
+    type Output = V;
+
+    #[inline]
+    fn index(&self, idx: K) -> &Self::Output {
+        self.get(idx).expect("index out of bounds")
+    }
+}
+
+impl<K: Key, V, A: Alloc> crate::ops::IndexMut<K> for KVecDeque<K, V, A> {
+    #[inline]
+    fn index_mut(&mut self, idx: K) -> &mut Self::Output {
+        self.get_mut(idx).expect("index out of bounds")
+    }
+}
+
+
+impl<K: Key, V: Clone, A: Alloc + Clone> Clone for KVecDeque<K, V, A> {
+    fn clone(&self) -> Self {
+        self.clone_in(self.alloc.clone())
+    }
+}
+
+
+impl<K: Key, V: crate::fmt::Debug, A: Alloc> crate::fmt::Debug for KVecDeque<K, V, A> {
+    fn fmt(&self, f: &mut crate::fmt::Formatter<'_>) -> crate::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+
+impl<K: Key, V, A: Alloc + Default> Default for KVecDeque<K, V, A> {
+    #[inline]
+    fn default() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+
+impl<K: Key, V, A: Alloc, I: Iterator<Item = V>> FromIn<I, A> for KVecDeque<K, V, A> {
+    #[inline]
+    fn from_in(alloc: A, iter: I) -> Self {
+        let (min_len, max_len) = iter.size_hint();
+        let cap = max_len.unwrap_or(min_len);
+
+        let mut result = KVecDeque::with_cap_in(alloc, cap);
+        for v in iter {
+            result.push_back(v);
+        }
+        return result;
+    }
+}
+
+impl<K: Key, V> FromIterator<V> for KVecDeque<K, V, GlobalAlloc> {
+    fn from_iter<T: IntoIterator<Item = V>>(iter: T) -> Self {
+        Self::from_in(GlobalAlloc, iter.into_iter())
+    }
+}
+
+impl<K: Key, V, A: Alloc> Extend<V> for KVecDeque<K, V, A> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = V>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+
+        let (min_len, max_len) = iter.size_hint();
+        let len = max_len.unwrap_or(min_len);
+
+        self.reserve_more(len);
+        for v in iter {
+            self.push_back(v);
+        }
+    }
+}
+
+impl<K: Key, V, A: Alloc> IntoIterator for KVecDeque<K, V, A> {
+    type IntoIter = IntoIter<K, V, A>;
+    type Item = V;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter { deque: self }
+    }
+}
+
+impl<'a, K: Key, V, A: Alloc> IntoIterator for &'a KVecDeque<K, V, A> {
+    type IntoIter = Iter<'a, V>;
+    type Item = &'a V;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Key, V, A: Alloc> IntoIterator for &'a mut KVecDeque<K, V, A> {
+    type IntoIter = IterMut<'a, V>;
+    type Item = &'a mut V;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+
+pub type Iter<'a, V> = core::iter::Chain<core::slice::Iter<'a, V>, core::slice::Iter<'a, V>>;
+
+pub type IterMut<'a, V> = core::iter::Chain<core::slice::IterMut<'a, V>, core::slice::IterMut<'a, V>>;
+
+
+pub struct IntoIter<K: Key, V, A: Alloc> {
+    deque: KVecDeque<K, V, A>,
+}
+
+impl<K: Key, V, A: Alloc> Iterator for IntoIter<K, V, A> {
+    type Item = V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.deque.pop_front()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.deque.len();
+        (len, Some(len))
+    }
+}
+
+impl<K: Key, V, A: Alloc> DoubleEndedIterator for IntoIter<K, V, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.deque.pop_back()
+    }
+}
+
+
+pub struct Drain<'a, K: Key, V, A: Alloc> {
+    deque: &'a mut KVecDeque<K, V, A>,
+    /// the start of the drained range.
+    begin: usize,
+    /// the next value to yield from the front.
+    idx: usize,
+    /// the end of the values, that haven't been yielded.
+    back: usize,
+    /// the end of the drained range.
+    end: usize,
+    /// the deque's length before draining.
+    old_len: usize,
+}
+
+impl<'a, K: Key, V, A: Alloc> Iterator for Drain<'a, K, V, A> {
+    type Item = V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx < self.back {
+            let ptr = self.deque.slot(self.deque.phys(self.idx));
+            self.idx += 1;
+            return Some(unsafe { ptr.read() });
+        }
+        return None;
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.idx;
+        (len, Some(len))
+    }
+}
+
+impl<'a, K: Key, V, A: Alloc> DoubleEndedIterator for Drain<'a, K, V, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.idx < self.back {
+            self.back -= 1;
+            let ptr = self.deque.slot(self.deque.phys(self.back));
+            return Some(unsafe { ptr.read() });
+        }
+        return None;
+    }
+}
+
+impl<'a, K: Key, V, A: Alloc> Drop for Drain<'a, K, V, A> {
+    fn drop(&mut self) {
+        // drop the remaining values.
+        while let Some(value) = self.next() {
+            drop(value);
+        }
+
+        let deque = &mut *self.deque;
+        let removed = self.end - self.begin;
+        let front_len = self.begin;
+        let back_len = self.old_len - self.end;
+
+        unsafe {
+            if front_len < back_len {
+                // move the front values back.
+                for i in (0..front_len).rev() {
+                    crate::mem::copy(
+                        deque.slot(deque.phys(i)),
+                        deque.slot(deque.phys(i + removed)),
+                        1);
+                }
+                deque.head = K::from_usize_unck(deque.phys(removed));
+            }
+            else {
+                // move the back values forward.
+                for i in 0..back_len {
+                    crate::mem::copy(
+                        deque.slot(deque.phys(self.end + i)),
+                        deque.slot(deque.phys(self.begin + i)),
+                        1);
+                }
+            }
+
+            deque.len = K::from_usize_unck(front_len + back_len);
+        }
+
+        deque.poison_gap();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents<K: Key, V: Clone, A: Alloc>(d: &KVecDeque<K, V, A>) -> std::vec::Vec<V> {
+        d.iter().cloned().collect()
+    }
+
+    #[test]
+    fn vec_deque_push_pop() {
+        let mut d: VecDeque<u32> = VecDeque::new();
+        assert_eq!(d.pop_front(), None);
+        assert_eq!(d.pop_back(), None);
+
+        for i in 0..10 {
+            if i % 2 == 0 { d.push_back(i); } else { d.push_front(i); }
+        }
+        assert_eq!(contents(&d), [9, 7, 5, 3, 1, 0, 2, 4, 6, 8]);
+        assert_eq!(d[0], 9);
+        assert_eq!(d.front(), Some(&9));
+        assert_eq!(d.back(), Some(&8));
+
+        let (a, b) = d.as_slices();
+        assert_eq!(a.len() + b.len(), 10);
+
+        assert_eq!(d.pop_front(), Some(9));
+        assert_eq!(d.pop_back(), Some(8));
+        *d.get_mut(0).unwrap() += 100;
+        assert_eq!(contents(&d), [107, 5, 3, 1, 0, 2, 4, 6]);
+
+        assert_eq!(d.make_contiguous(), &[107, 5, 3, 1, 0, 2, 4, 6]);
+        assert_eq!(d.as_slices().1.len(), 0);
+
+        d.clear();
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn vec_deque_grow_wrapped() {
+        // exercise both fix up strategies.
+        for front in 0..8 {
+            let mut d: VecDeque<u32> = VecDeque::with_cap(8);
+            for i in 0..8 - front { d.push_back(i); }
+            for i in 0..front { d.push_front(100 + i); }
+            assert_eq!(d.cap(), 8);
+
+            let expected = contents(&d);
+            d.push_back(1000);
+            assert!(d.cap() > 8);
+            assert_eq!(&contents(&d)[..8], &expected[..]);
+            assert_eq!(d.back(), Some(&1000));
+
+            d.trim_exact();
+            assert_eq!(d.cap(), 9);
+            assert_eq!(&contents(&d)[..8], &expected[..]);
+        }
+    }
+
+    #[test]
+    fn vec_deque_rotate() {
+        let mut d: VecDeque<u32> = (0..5).collect();
+        d.rotate_left(2);
+        assert_eq!(contents(&d), [2, 3, 4, 0, 1]);
+        d.rotate_right(2);
+        assert_eq!(contents(&d), [0, 1, 2, 3, 4]);
+        d.rotate_left(4);
+        assert_eq!(contents(&d), [4, 0, 1, 2, 3]);
+
+        // full deques just move the head.
+        let mut d: VecDeque<u32> = VecDeque::with_cap(4);
+        d.extend(0..4);
+        d.rotate_left(3);
+        assert_eq!(contents(&d), [3, 0, 1, 2]);
+        d.rotate_right(1);
+        assert_eq!(contents(&d), [2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn vec_deque_drain() {
+        for begin in 0..6 {
+            for end in begin..6 {
+                let mut d: VecDeque<u32> = VecDeque::with_cap(6);
+                d.extend(2..6);
+                d.push_front(1);
+                d.push_front(0);
+
+                let drained: std::vec::Vec<u32> = d.drain(begin as u32..end as u32).collect();
+                assert_eq!(drained, (begin as u32..end as u32).collect::<std::vec::Vec<_>>());
+
+                let expected: std::vec::Vec<u32> = (0..6).filter(|i| *i < begin as u32 || *i >= end as u32).collect();
+                assert_eq!(contents(&d), expected);
+            }
+        }
+
+        // unconsumed & back.
+        let mut d: VecDeque<std::string::String> = (0..5).map(|i| i.to_string()).collect();
+        let mut drain = d.drain(1..=3);
+        assert_eq!(drain.next_back().as_deref(), Some("3"));
+        drop(drain);
+        assert_eq!(contents(&d), ["0", "4"]);
+        d.drain(..);
+        assert!(d.is_empty());
+    }
+
+    #[test]
+    fn vec_deque_arena() {
+        let arena = crate::arena::Arena::new();
+        let mut d: VecDeque<u64, _> = VecDeque::new_in(&arena);
+        for i in 0..100 {
+            d.push_back(i);
+            d.push_front(i);
+        }
+        assert_eq!(d.len(), 200);
+        assert_eq!(d.iter().sum::<u64>(), 2*(0..100).sum::<u64>());
+
+        let d2 = d.clone_in(&arena);
+        assert_eq!(contents(&d2), contents(&d));
+        assert_eq!(d.into_iter().rev().next(), Some(99));
+    }
+
+    #[test]
+    fn vec_deque_arena_grow_wrapped() {
+        // the deque is the arena's last allocation,
+        // so growing reallocs in place.
+        for (front, back) in [(1, 3), (3, 1), (2, 2)] {
+            let arena = crate::arena::Arena::new();
+            let mut d: VecDeque<u64, _> = VecDeque::with_cap_in(&arena, 4);
+            for i in 0..back { d.push_back(i); }
+            for i in 0..front { d.push_front(100 + i); }
+            assert_eq!(d.len(), 4);
+            let (a, b) = d.as_slices();
+            assert!(a.len() > 0 && b.len() > 0);
+
+            let expected = contents(&d);
+            d.reserve(20);
+            assert!(d.cap() >= 20);
+            assert_eq!(contents(&d), expected);
+
+            d.push_back(7);
+            d.push_front(8);
+            assert_eq!(d.len(), 6);
+        }
+    }
+}
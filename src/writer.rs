@@ -0,0 +1,383 @@
+use crate::key::Key;
+use crate::alloc::Alloc;
+use crate::vec::KVec;
+use crate::slice_vec::SliceVec;
+
+
+/// a byte buffer, that can be appended to.
+///
+/// - implemented for byte vecs and `SliceVec<u8>`.
+pub trait Buffer {
+    /// number of bytes in the buffer.
+    fn len(&self) -> usize;
+
+    /// appends all of `bytes`, or nothing.
+    ///
+    /// - returns whether the bytes were written.
+    fn write(&mut self, bytes: &[u8]) -> bool;
+
+    /// appends `n` zero bytes, or nothing.
+    ///
+    /// - returns whether the bytes were written.
+    fn write_zeros(&mut self, n: usize) -> bool;
+
+    /// the bytes written so far.
+    ///
+    /// - used for back-patching.
+    fn as_mut_slice(&mut self) -> &mut [u8];
+}
+
+impl<K: Key, A: Alloc> Buffer for KVec<K, u8, A> {
+    #[inline(always)]
+    fn len(&self) -> usize { KVec::len(self) }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) -> bool {
+        self.extend_from_slice(bytes);
+        return true;
+    }
+
+    #[inline]
+    fn write_zeros(&mut self, n: usize) -> bool {
+        let len = KVec::len(self);
+        self.resize(len + n, 0);
+        return true;
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] { KVec::as_mut_slice(self) }
+}
+
+impl<'a> Buffer for SliceVec<'a, u8> {
+    #[inline(always)]
+    fn len(&self) -> usize { SliceVec::len(self) }
+
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) -> bool {
+        if bytes.len() > self.remaining() {
+            return false;
+        }
+        let n = self.extend_from_slice(bytes);
+        debug_assert_eq!(n, bytes.len());
+        return true;
+    }
+
+    #[inline]
+    fn write_zeros(&mut self, n: usize) -> bool {
+        if n > self.remaining() {
+            return false;
+        }
+        let mut left = n;
+        while left > 0 {
+            left -= self.extend_from_slice(&[0; 64][..left.min(64)]);
+        }
+        return true;
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] { SliceVec::as_mut_slice(self) }
+}
+
+impl<B: Buffer + ?Sized> Buffer for &mut B {
+    #[inline(always)]
+    fn len(&self) -> usize { (**self).len() }
+
+    #[inline(always)]
+    fn write(&mut self, bytes: &[u8]) -> bool { (**self).write(bytes) }
+
+    #[inline(always)]
+    fn write_zeros(&mut self, n: usize) -> bool { (**self).write_zeros(n) }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] { (**self).as_mut_slice() }
+}
+
+
+/// a binary writer. the counterpart to `Reader`.
+///
+/// - appends to a `Buffer`.
+/// - offsets are relative to the length of the buffer,
+///   when the writer was created.
+/// - writes are all or nothing. they return `Err(())`,
+///   if the buffer ran out of space.
+pub struct Writer<B: Buffer> {
+    buffer: B,
+    start: usize,
+}
+
+/// a reserved range of `N` bytes. see `Writer::reserve_patch`.
+#[must_use]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Patch<const N: usize> {
+    offset: usize,
+}
+
+impl<const N: usize> Patch<N> {
+    /// the offset of the reserved bytes.
+    #[inline(always)]
+    pub fn offset(&self) -> usize { self.offset }
+
+    /// the offset just after the reserved bytes.
+    ///
+    /// - useful for computing sizes of the data after the patch.
+    #[inline(always)]
+    pub fn end(&self) -> usize { self.offset + N }
+}
+
+impl<B: Buffer> Writer<B> {
+    #[inline]
+    pub fn new(buffer: B) -> Self {
+        let start = buffer.len();
+        Writer { buffer, start }
+    }
+
+    #[inline(always)]
+    pub fn buffer(&self) -> &B { &self.buffer }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> B { self.buffer }
+
+
+    /// returns the current offset, relative to the start of the writer.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// number of bytes written.
+    ///
+    /// - same as `offset`.
+    #[inline(always)]
+    pub fn written(&self) -> usize {
+        self.offset()
+    }
+
+    /// returns the bytes written by this writer.
+    #[inline]
+    pub fn written_slice_mut(&mut self) -> &mut [u8] {
+        let start = self.start;
+        &mut self.buffer.as_mut_slice()[start..]
+    }
+
+
+    #[inline]
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), ()> {
+        if self.buffer.write(bytes) {
+            return Ok(());
+        }
+        return Err(());
+    }
+
+    #[inline]
+    pub fn write_zeros(&mut self, n: usize) -> Result<(), ()> {
+        if self.buffer.write_zeros(n) {
+            return Ok(());
+        }
+        return Err(());
+    }
+
+    /// writes zeros, until the offset is a multiple of `align`.
+    ///
+    /// - `align` must be a power of two.
+    #[inline]
+    pub fn align_to(&mut self, align: usize) -> Result<(), ()> {
+        assert!(align.is_power_of_two());
+        let offset = self.offset();
+        let padding = crate::num::ceil_to_multiple_pow2(offset, align) - offset;
+        return self.write_zeros(padding);
+    }
+
+
+    /// writes `N` zero bytes, to be filled in later.
+    ///
+    /// - use `patch` or `patch_bytes` to fill them in.
+    /// - useful for length fields.
+    #[inline]
+    pub fn reserve_patch<const N: usize>(&mut self) -> Result<Patch<N>, ()> {
+        let offset = self.offset();
+        self.write_zeros(N)?;
+        return Ok(Patch { offset });
+    }
+
+    /// the reserved bytes of `patch`.
+    ///
+    /// # panics
+    /// - if `patch` wasn't returned by this writer's `reserve_patch`.
+    #[inline]
+    pub fn patch_bytes<const N: usize>(&mut self, patch: Patch<N>) -> &mut [u8; N] {
+        let slice = &mut self.written_slice_mut()[patch.offset..patch.offset + N];
+        return slice.try_into().expect("unreachable");
+    }
+
+    /// overwrites the reserved bytes of `patch`.
+    ///
+    /// # panics
+    /// - if `patch` wasn't returned by this writer's `reserve_patch`.
+    #[inline]
+    pub fn patch<const N: usize>(&mut self, patch: Patch<N>, bytes: [u8; N]) {
+        *self.patch_bytes(patch) = bytes;
+    }
+
+
+    #[inline]
+    pub fn write_u8(&mut self, value: u8) -> Result<(), ()> {
+        self.write_bytes(&[value])
+    }
+
+    #[inline]
+    pub fn write_u16_le(&mut self, value: u16) -> Result<(), ()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    #[inline]
+    pub fn write_u16_be(&mut self, value: u16) -> Result<(), ()> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    #[inline]
+    pub fn write_u32_le(&mut self, value: u32) -> Result<(), ()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    #[inline]
+    pub fn write_u32_be(&mut self, value: u32) -> Result<(), ()> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    #[inline]
+    pub fn write_u64_le(&mut self, value: u64) -> Result<(), ()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    #[inline]
+    pub fn write_u64_be(&mut self, value: u64) -> Result<(), ()> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+    #[inline]
+    pub fn write_u128_le(&mut self, value: u128) -> Result<(), ()> {
+        self.write_bytes(&value.to_le_bytes())
+    }
+
+    #[inline]
+    pub fn write_u128_be(&mut self, value: u128) -> Result<(), ()> {
+        self.write_bytes(&value.to_be_bytes())
+    }
+
+
+    /// writes the in-memory representation of `value`.
+    ///
+    /// # safety:
+    /// - all bytes of `T` must be initialized.
+    ///   in particular, `T` must not have padding.
+    #[inline]
+    pub unsafe fn write_ctype<T>(&mut self, value: &T) -> Result<(), ()> {
+        let bytes = unsafe {
+            core::slice::from_raw_parts(
+                (value as *const T).cast::<u8>(),
+                size_of::<T>())
+        };
+        return self.write_bytes(bytes);
+    }
+}
+
+impl<B: Buffer> Buffer for Writer<B> {
+    #[inline(always)]
+    fn len(&self) -> usize { self.offset() }
+
+    #[inline(always)]
+    fn write(&mut self, bytes: &[u8]) -> bool { self.buffer.write(bytes) }
+
+    #[inline(always)]
+    fn write_zeros(&mut self, n: usize) -> bool { self.buffer.write_zeros(n) }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] { self.written_slice_mut() }
+}
+
+
+impl<B: Buffer> core::fmt::Debug for Writer<B> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Writer")
+            .field("start", &self.start)
+            .field("written", &self.written())
+            .finish()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec::ZVec;
+    use crate::mem::MaybeUninit;
+    use crate::reader::Reader;
+
+    #[test]
+    fn writer_vec() {
+        let mut buffer: ZVec<u8> = ZVec::new();
+        buffer.push(0xaa);
+
+        let mut w = Writer::new(&mut buffer);
+        assert_eq!(w.offset(), 0);
+
+        w.write_u8(1).unwrap();
+        let len = w.reserve_patch::<4>().unwrap();
+        assert_eq!(len.offset(), 1);
+        w.write_u16_le(0x0302).unwrap();
+        w.write_u16_be(0x0405).unwrap();
+        w.align_to(8).unwrap();
+        assert_eq!(w.offset(), 16);
+        w.align_to(8).unwrap();
+        assert_eq!(w.offset(), 16);
+        w.write_u32_le(0x0908_0706).unwrap();
+        w.write_u32_be(0x0a0b_0c0d).unwrap();
+        w.write_u64_le(0x1122_3344_5566_7788).unwrap();
+        w.write_u64_be(0x1122_3344_5566_7788).unwrap();
+        w.write_u128_le(1).unwrap();
+        w.write_u128_be(2).unwrap();
+        unsafe { w.write_ctype(&0xdead_beefu32).unwrap() };
+
+        let size = (w.offset() - len.end()) as u32;
+        w.patch(len, size.to_le_bytes());
+        assert_eq!(w.written(), 76);
+        drop(w);
+
+        assert_eq!(buffer[0], 0xaa);
+        let mut r = Reader::new(&buffer[1..]);
+        assert_eq!(r.next_u8_le(), Some(1));
+        assert_eq!(r.next_u32_le(), Some(71));
+        assert_eq!(r.next_n(4), Some([2, 3, 4, 5].as_slice()));
+        assert_eq!(r.next_n(7), Some([0; 7].as_slice()));
+        assert_eq!(r.next_u32_le(), Some(0x0908_0706));
+        assert_eq!(r.next_u32_be(), Some(0x0a0b_0c0d));
+        assert_eq!(r.next_u64_le(), Some(0x1122_3344_5566_7788));
+        assert_eq!(r.next_u64_be(), Some(0x1122_3344_5566_7788));
+        assert_eq!(r.next_u128_le(), Some(1));
+        assert_eq!(r.next_u128_be(), Some(2));
+        assert_eq!(unsafe { r.next_ctype::<u32>() }, Some(0xdead_beef));
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn writer_slice_vec() {
+        let mut buffer = [MaybeUninit::uninit(); 7];
+        let mut w = Writer::new(SliceVec::new(&mut buffer));
+
+        w.write_u32_be(0x0102_0304).unwrap();
+        assert_eq!(w.write_u32_be(0), Err(()));
+        assert_eq!(w.offset(), 4);
+        assert_eq!(w.align_to(8), Err(()));
+        assert_eq!(w.offset(), 4);
+
+        let p = w.reserve_patch::<2>().unwrap();
+        w.patch_bytes(p)[1] = 7;
+        assert_eq!(w.reserve_patch::<2>(), Err(()));
+        w.write_u8(9).unwrap();
+        assert_eq!(w.write_u8(9), Err(()));
+
+        let buffer = w.into_inner();
+        assert_eq!(buffer.as_slice(), &[1, 2, 3, 4, 0, 7, 9]);
+    }
+}